@@ -1,112 +1,275 @@
-use crate::data_structures::MergedData;
-use std::collections::HashMap;
+use crate::correlation::CorrelationMethod;
+use crate::data_structures::{season_label, AnalysisScope, MergedData, Player, Qualifier, StatBasis, Team};
+use crate::franchise::FranchiseRegistry;
+use crate::registry::{PlayerMetadata, PLAYER_METADATA_HEADERS};
+use crate::significance::{CorrelationOptions, Significance};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::fs::File;
+use std::ops::RangeInclusive;
 use csv::Writer;
 
+// `seasons` is the player's season for single player rows and the analysed range for
+// results over all players.
 #[derive(Clone)]
 pub struct CorrelationResult {
+    pub seasons: RangeInclusive<u32>,
+    pub method: CorrelationMethod,
     pub player_id: u32,
     pub player_name: String,
     pub team_name: String,
     pub statistic_name: String,
     pub correlation_coefficient: f64,
+    pub significance: Significance,
 }
 
-pub fn correlate_statistics(merged_data: &[MergedData]) -> Vec<CorrelationResult> {
+// A player's shooting percentage next to the team's for one season. It says how far above
+// or below the team the player shot, which is not a correlation. `team_edge` is the team's
+// value minus what its opponents shot, and the player's honors for the season are carried
+// along so shooting can be compared with recognition.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShootingDifferential {
+    pub season: u32,
+    pub player_id: u32,
+    pub player_name: String,
+    pub team_name: String,
+    pub statistic_name: String,
+    pub player_value: f64,
+    pub team_value: f64,
+    pub team_edge: Option<f64>,
+    pub all_star: bool,
+    pub all_nba_team: Option<u8>,
+    pub mvp_share: Option<f64>,
+}
+
+impl ShootingDifferential {
+    pub fn difference(&self) -> f64 {
+        self.player_value - self.team_value
+    }
+
+    pub fn ratio(&self) -> f64 {
+        self.player_value / self.team_value
+    }
+}
+
+type ShootingStatistic = fn(&Qualifier, &Player) -> Option<f64>;
+
+// The player percentages every analysis looks at, each read through the qualifier.
+const SHOOTING_STATISTICS: [(&str, ShootingStatistic); 3] = [
+    ("FG Percent", Qualifier::fg_percent),
+    ("FG Percent from 2P Range", Qualifier::two_point_percent),
+    ("FG Percent from 3P Range", Qualifier::three_point_percent),
+];
+
+// A statistic is only compared when both the player and the team have a value for it and
+// the player qualifies for it.
+pub fn shooting_differentials(merged_data: &[MergedData], scope: &AnalysisScope) -> Vec<ShootingDifferential> {
     let mut results = Vec::new();
 
-    for data in merged_data {
+    for data in merged_data.iter().filter(|d| scope.includes(d.team.season, &d.team.lg)) {
         let player = &data.player;
         let team = &data.team;
+        let qualifier = scope.qualifier(player.season);
+        let team_values = [
+            (team.fg_percentage, team.fg_percentage_edge()),
+            (team.two_point_percentage, team.two_point_percentage_edge()),
+            (team.three_point_percentage, team.three_point_percentage_edge()),
+        ];
+
+        for ((statistic, player_value), (team_value, team_edge)) in SHOOTING_STATISTICS.into_iter().zip(team_values) {
+            let (Some(player_value), Some(team_value)) = (player_value(qualifier, player), team_value) else {
+                continue;
+            };
+
+            results.push(ShootingDifferential {
+                season: player.season,
+                player_id: player.id,
+                player_name: player.name.clone(),
+                team_name: team.name.clone(),
+                statistic_name: statistic.to_string(),
+                player_value,
+                team_value,
+                team_edge,
+                all_star: data.honors.all_star,
+                all_nba_team: data.honors.all_nba_team,
+                mvp_share: data.honors.mvp_share,
+            });
+        }
+    }
 
-        let fg_percent_diff = player.fg_percent - team.fg_percentage;
-        let fg_percent_ratio = player.fg_percent / team.fg_percentage;
+    results
+}
 
-        let fg_percent_from_x2p_range_diff = player.fg_percent_from_x2p_range - team.two_point_percentage;
-        let fg_percent_from_x2p_range_ratio = player.fg_percent_from_x2p_range / team.two_point_percentage;
+// Players need this many seasons with both values before their career is correlated.
+const MIN_CAREER_SEASONS: usize = 3;
 
-        let fg_percent_from_x3p_range_diff = player.fg_percent_from_x3p_range - team.three_point_percentage;
-        let fg_percent_from_x3p_range_ratio = player.fg_percent_from_x3p_range / team.three_point_percentage;
+// The team outcomes a player's career is correlated with, with points read in `basis`.
+fn career_outcomes(basis: StatBasis) -> [TeamOutcome; 3] {
+    [TeamOutcome::Points(basis), TeamOutcome::WinPercentage, TeamOutcome::NetRating]
+}
 
-        let fg_percent_diff_result = CorrelationResult {
-            player_id: player.id,
-            player_name: player.name.clone(),
-            team_name: team.name.clone(),
-            statistic_name: "FG Percent Diff".to_string(),
-            correlation_coefficient: fg_percent_diff,
-        };
-        results.push(fg_percent_diff_result);
-
-        let fg_percent_ratio_result = CorrelationResult {
-            player_id: player.id,
-            player_name: player.name.clone(),
-            team_name: team.name.clone(),
-            statistic_name: "FG Percent Ratio".to_string(),
-            correlation_coefficient: fg_percent_ratio,
-        };
-        results.push(fg_percent_ratio_result);
-
-        let fg_percent_from_x2p_range_diff_result = CorrelationResult {
-            player_id: player.id,
-            player_name: player.name.clone(),
-            team_name: team.name.clone(),
-            statistic_name: "FG Percent from 2P Range Diff".to_string(),
-            correlation_coefficient: fg_percent_from_x2p_range_diff,
-        };
-        results.push(fg_percent_from_x2p_range_diff_result);
-
-        let fg_percent_from_x2p_range_ratio_result = CorrelationResult {
-            player_id: player.id,
-            player_name: player.name.clone(),
-            team_name: team.name.clone(),
-            statistic_name: "FG Percent from 2P Range Ratio".to_string(),
-            correlation_coefficient: fg_percent_from_x2p_range_ratio,
-        };
-        results.push(fg_percent_from_x2p_range_ratio_result);
-
-        let fg_percent_from_x3p_range_diff_result = CorrelationResult {
-            player_id: player.id,
-            player_name: player.name.clone(),
-            team_name: team.name.clone(),
-            statistic_name: "FG Percent from 3P Range Diff".to_string(),
-            correlation_coefficient: fg_percent_from_x3p_range_diff,
-        };
-        results.push(fg_percent_from_x3p_range_diff_result);
-
-        let fg_percent_from_x3p_range_ratio_result = CorrelationResult {
-            player_id: player.id,
-            player_name: player.name.clone(),
-            team_name: team.name.clone(),
-            statistic_name: "FG Percent from 3P Range Ratio".to_string(),
-            correlation_coefficient: fg_percent_from_x3p_range_ratio,
-        };
-        results.push(fg_percent_from_x3p_range_ratio_result);
+// Correlates each player's shooting with the outcomes of the teams they played for, one
+// pair per season (per team in a season split by a trade, weighted by the stint's
+// `stint_weight`). Players with a season in
+// `scope` are included and every season of theirs in `merged_data` is used, so a single
+// season scope still looks at whole careers. Each season is qualified with that season's
+// qualifier. Results are labelled with the seasons used and the franchises the player
+// played for.
+pub fn correlate_player_careers(
+    merged_data: &[MergedData],
+    scope: &AnalysisScope,
+    options: &CorrelationOptions,
+    franchises: &FranchiseRegistry,
+) -> Vec<CorrelationResult> {
+    let in_scope: HashSet<u32> = merged_data
+        .iter()
+        .filter(|d| scope.includes(d.team.season, &d.team.lg))
+        .map(|d| d.player.id)
+        .collect();
+    let mut careers: HashMap<u32, Vec<&MergedData>> = HashMap::new();
+    for data in merged_data.iter().filter(|d| in_scope.contains(&d.player.id) && scope.leagues.matches(&d.team.lg)) {
+        careers.entry(data.player.id).or_default().push(data);
+    }
+
+    let mut player_ids: Vec<u32> = careers.keys().copied().collect();
+    player_ids.sort();
+
+    let mut results = Vec::new();
+    for player_id in player_ids {
+        let career = careers.get_mut(&player_id).unwrap();
+        career.sort_by_key(|d| d.team.season);
+        let franchise_names = franchise_names(career, franchises);
+        for (statistic, player_value) in SHOOTING_STATISTICS {
+            for outcome in career_outcomes(scope.team_basis) {
+                let pairs: Vec<(u32, f64, f64, f64)> = career
+                    .iter()
+                    .filter_map(|d| {
+                        let x = player_value(scope.qualifier(d.player.season), &d.player)?;
+                        Some((d.player.season, x, outcome.value(&d.team)?, d.player.stint_weight))
+                    })
+                    .collect();
+                let mut seasons: Vec<u32> = pairs.iter().map(|(season, _, _, _)| *season).collect();
+                seasons.sort();
+                seasons.dedup();
+                if seasons.len() < MIN_CAREER_SEASONS {
+                    continue;
+                }
+
+                let x_values: Vec<f64> = pairs.iter().map(|(_, x, _, _)| *x).collect();
+                let outcome_values: Vec<f64> = pairs.iter().map(|(_, _, y, _)| *y).collect();
+                let weights: Vec<f64> = pairs.iter().map(|(_, _, _, w)| *w).collect();
+                let (correlation_coefficient, significance) = options.correlate(&x_values, &outcome_values, &weights);
+                results.push(CorrelationResult {
+                    seasons: seasons[0]..=seasons[seasons.len() - 1],
+                    method: options.method,
+                    player_id,
+                    player_name: career[0].player.name.clone(),
+                    team_name: franchise_names.clone(),
+                    statistic_name: format!("{} vs {}", statistic, outcome.label()),
+                    correlation_coefficient,
+                    significance,
+                });
+            }
+        }
     }
 
     results
 }
 
-pub struct PlayoffCorrelationResults {
-    pub all_players_correlation: HashMap<u32, CorrelationResult>,
+// The franchises of a career sorted by season, oldest first, under the name each one
+// played under most recently. A relocated team is listed once.
+fn franchise_names(career: &[&MergedData], franchises: &FranchiseRegistry) -> String {
+    let mut franchise_ids: Vec<String> = Vec::new();
+    let mut names: Vec<&str> = Vec::new();
+    for data in career {
+        let franchise_id = franchises.franchise_key(data.team.season, &data.team.abbreviation);
+        if !franchise_ids.contains(&franchise_id) {
+            names.push(franchises.franchise_name(&franchise_id).unwrap_or(&data.team.name));
+            franchise_ids.push(franchise_id);
+        }
+    }
+    names.join(", ")
 }
 
-pub fn analyze_playoff_correlation(merged_data: &[MergedData]) -> PlayoffCorrelationResults {
-    let mut all_players_correlation = HashMap::new();
+// One result per shooting statistic, in the order of SHOOTING_STATISTICS.
+pub struct OutcomeCorrelationResults {
+    pub all_players_correlation: Vec<CorrelationResult>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TeamOutcome {
+    Playoffs,
+    Points(StatBasis),
+    WinPercentage,
+    NetRating,
+}
 
-    let mut fg_percent_values = Vec::new();
-    let mut fg_percent_from_x2p_range_values = Vec::new();
-    let mut fg_percent_from_x3p_range_values = Vec::new();
-    let mut playoff_values = Vec::new();
+impl TeamOutcome {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TeamOutcome::Playoffs => "Playoffs",
+            TeamOutcome::Points(StatBasis::PerGame) => "Points Per Game",
+            TeamOutcome::Points(StatBasis::Per100Poss) => "Points Per 100 Poss",
+            TeamOutcome::Points(StatBasis::Totals) => "Total Points",
+            TeamOutcome::WinPercentage => "Win Percentage",
+            TeamOutcome::NetRating => "Net Rating",
+        }
+    }
 
-    for data in merged_data {
+    // Win percentage and net rating come from Team Summaries.csv and are None for teams
+    // without a merged summary. Points outside per game need a merged team season.
+    pub fn value(&self, team: &Team) -> Option<f64> {
+        match self {
+            TeamOutcome::Playoffs => Some(team.playoffs as u8 as f64),
+            TeamOutcome::Points(basis) => team.points(*basis),
+            TeamOutcome::WinPercentage => team.summary.as_ref()?.win_percentage(),
+            TeamOutcome::NetRating => team.summary.as_ref()?.n_rtg,
+        }
+    }
+}
+
+pub fn analyze_playoff_correlation(
+    merged_data: &[MergedData],
+    scope: &AnalysisScope,
+    options: &CorrelationOptions,
+) -> OutcomeCorrelationResults {
+    analyze_outcome_correlation(merged_data, TeamOutcome::Playoffs, scope, options)
+}
+
+// Missing and unqualified values are deleted pairwise, so each statistic uses every
+// player that has it and qualifies for it. Each pair is weighted by the player row's
+// `stint_weight`. Statistic names are suffixed with the outcome unless it is the playoff flag.
+pub fn analyze_outcome_correlation(
+    merged_data: &[MergedData],
+    outcome: TeamOutcome,
+    scope: &AnalysisScope,
+    options: &CorrelationOptions,
+) -> OutcomeCorrelationResults {
+    let mut all_players_correlation = Vec::new();
+
+    let mut fg_percent_pairs = (Vec::new(), Vec::new(), Vec::new());
+    let mut fg_percent_from_x2p_range_pairs = (Vec::new(), Vec::new(), Vec::new());
+    let mut fg_percent_from_x3p_range_pairs = (Vec::new(), Vec::new(), Vec::new());
+
+    for data in merged_data.iter().filter(|d| scope.includes(d.team.season, &d.team.lg)) {
         let player = &data.player;
-        let team = &data.team;
+        let qualifier = scope.qualifier(player.season);
+        let Some(outcome_value) = outcome.value(&data.team) else {
+            continue;
+        };
 
-        fg_percent_values.push(player.fg_percent);
-        fg_percent_from_x2p_range_values.push(player.fg_percent_from_x2p_range);
-        fg_percent_from_x3p_range_values.push(player.fg_percent_from_x3p_range);
-        playoff_values.push(team.playoffs as u8 as f64);
+        let statistics = [
+            (qualifier.fg_percent(player), &mut fg_percent_pairs),
+            (qualifier.two_point_percent(player), &mut fg_percent_from_x2p_range_pairs),
+            (qualifier.three_point_percent(player), &mut fg_percent_from_x3p_range_pairs),
+        ];
+
+        for (value, (x_values, outcome_values, weights)) in statistics {
+            if let Some(value) = value {
+                x_values.push(value);
+                outcome_values.push(outcome_value);
+                weights.push(player.stint_weight);
+            }
+        }
     }
 
     let statistic_names = [
@@ -115,80 +278,170 @@ pub fn analyze_playoff_correlation(merged_data: &[MergedData]) -> PlayoffCorrela
         "FG Percent from 3P Range",
     ];
 
-    let x_values_array = [
-        &fg_percent_values,
-        &fg_percent_from_x2p_range_values,
-        &fg_percent_from_x3p_range_values,
+    let pairs_array = [
+        &fg_percent_pairs,
+        &fg_percent_from_x2p_range_pairs,
+        &fg_percent_from_x3p_range_pairs,
     ];
 
-    for (i, x_values) in x_values_array.iter().enumerate() {
-        let statistic_name = statistic_names[i].to_string();
-        let correlation_coefficient = calculate_correlation(x_values, &playoff_values);
+    for (i, (x_values, outcome_values, weights)) in pairs_array.iter().enumerate() {
+        let statistic_name = match outcome {
+            TeamOutcome::Playoffs => statistic_names[i].to_string(),
+            _ => format!("{} vs {}", statistic_names[i], outcome.label()),
+        };
+        let (correlation_coefficient, significance) = options.correlate(x_values, outcome_values, weights);
 
         let result = CorrelationResult {
+            seasons: scope.seasons.clone(),
+            method: options.method,
             player_id: 0,
             player_name: "All Players".to_string(),
             team_name: "".to_string(),
             statistic_name,
             correlation_coefficient,
+            significance,
         };
 
-        all_players_correlation.insert(i as u32, result);
+        all_players_correlation.push(result);
     }
 
-    PlayoffCorrelationResults {
+    OutcomeCorrelationResults {
         all_players_correlation,
     }
 }
 
-fn calculate_correlation(x_values: &[f64], y_values: &[f64]) -> f64 {
-    let n = x_values.len() as f64;
-    let sum_x = x_values.iter().sum::<f64>();
-    let sum_y = y_values.iter().sum::<f64>();
-    let sum_x_sq = x_values.iter().map(|x| x * x).sum::<f64>();
-    let sum_y_sq = y_values.iter().map(|y| y * y).sum::<f64>();
-    let sum_xy = x_values.iter().zip(y_values.iter()).map(|(x, y)| x * y).sum::<f64>();
-
-    let numerator = n * sum_xy - sum_x * sum_y;
-    let denominator = ((n * sum_x_sq - sum_x * sum_x) * (n * sum_y_sq - sum_y * sum_y)).sqrt();
-
-    if denominator != 0.0 {
-        numerator / denominator
-    } else {
-        f64::NAN
+// Treats `results` as one family of tests: their p-values are adjusted together with
+// `options.adjustment` and flagged against the options' significance level.
+pub fn adjust_p_values(results: &mut [CorrelationResult], options: &CorrelationOptions) {
+    options
+        .adjustment
+        .apply(results.iter_mut().map(|r| &mut r.significance), options.significance_level());
+}
+
+// Player metadata columns are appended when `player_metadata` (keyed by player id and
+// season) is given; players missing from the map get empty cells.
+pub fn write_differentials_to_csv(
+    differentials: &[ShootingDifferential],
+    file_path: &str,
+    player_metadata: Option<&HashMap<(u32, u32), PlayerMetadata>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut writer = Writer::from_path(file_path)?;
+
+    let mut headers = vec![
+        "Season",
+        "Player ID",
+        "Player Name",
+        "Team Name",
+        "Statistic Name",
+        "Player Value",
+        "Team Value",
+        "Difference",
+        "Ratio",
+        "Team Edge",
+        "All-Star",
+        "All-NBA Team",
+        "MVP Share",
+    ];
+    if player_metadata.is_some() {
+        headers.extend(PLAYER_METADATA_HEADERS);
     }
+    writer.write_record(&headers)?;
+
+    for differential in differentials {
+        let mut record = vec![
+            differential.season.to_string(),
+            differential.player_id.to_string(),
+            differential.player_name.clone(),
+            differential.team_name.clone(),
+            differential.statistic_name.clone(),
+            differential.player_value.to_string(),
+            differential.team_value.to_string(),
+            differential.difference().to_string(),
+            differential.ratio().to_string(),
+            differential.team_edge.map(|e| e.to_string()).unwrap_or_default(),
+            differential.all_star.to_string(),
+            differential.all_nba_team.map(|t| t.to_string()).unwrap_or_default(),
+            differential.mvp_share.map(|s| s.to_string()).unwrap_or_default(),
+        ];
+        if let Some(player_metadata) = player_metadata {
+            let metadata = player_metadata
+                .get(&(differential.player_id, differential.season))
+                .cloned()
+                .unwrap_or_default();
+            record.extend(metadata.to_record());
+        }
+        writer.write_record(&record)?;
+    }
+
+    writer.flush()?;
+    Ok(())
 }
 
+// Player metadata columns are appended when `player_metadata` (keyed by player id and
+// season) is given; players missing from the map get empty cells. The bootstrap interval
+// cells are empty when it was not computed.
 pub fn write_correlations_to_csv(
     correlations: &[CorrelationResult],
     file_path: &str,
     include_team_name: bool,
+    player_metadata: Option<&HashMap<(u32, u32), PlayerMetadata>>,
 ) -> Result<(), Box<dyn Error>> {
     let mut writer = Writer::from_path(file_path)?;
 
+    let mut headers = vec!["Season", "Player ID", "Player Name"];
     if include_team_name {
-        writer.write_record(&["Player ID", "Player Name", "Team Name", "Statistic Name", "Correlation Coefficient"])?;
-    } else {
-        writer.write_record(&["Player ID", "Player Name", "Statistic Name", "Correlation Coefficient"])?;
+        headers.push("Team Name");
     }
+    headers.extend([
+        "Statistic Name",
+        "Method",
+        "Correlation Coefficient",
+        "N",
+        "P Value",
+        "Adjustment",
+        "Adjusted P Value",
+        "Significant",
+        "CI Low",
+        "CI High",
+        "Bootstrap CI Low",
+        "Bootstrap CI High",
+    ]);
+    if player_metadata.is_some() {
+        headers.extend(PLAYER_METADATA_HEADERS);
+    }
+    writer.write_record(&headers)?;
 
     for result in correlations {
+        let mut record = vec![season_label(&result.seasons), result.player_id.to_string(), result.player_name.clone()];
         if include_team_name {
-            writer.write_record(&[
-                result.player_id.to_string(),
-                result.player_name.clone(),
-                result.team_name.clone(),
-                result.statistic_name.clone(),
-                result.correlation_coefficient.to_string(),
-            ])?;
-        } else {
-            writer.write_record(&[
-                result.player_id.to_string(),
-                result.player_name.clone(),
-                result.statistic_name.clone(),
-                result.correlation_coefficient.to_string(),
-            ])?;
+            record.push(result.team_name.clone());
+        }
+        record.push(result.statistic_name.clone());
+        record.push(result.method.to_string());
+        record.push(result.correlation_coefficient.to_string());
+        let significance = &result.significance;
+        let (ci_low, ci_high) = significance.confidence_interval;
+        record.extend([
+            significance.n.to_string(),
+            significance.p_value.to_string(),
+            significance.adjustment.to_string(),
+            significance.adjusted_p_value.to_string(),
+            significance.significant.to_string(),
+            ci_low.to_string(),
+            ci_high.to_string(),
+        ]);
+        match significance.bootstrap_interval {
+            Some((low, high)) => record.extend([low.to_string(), high.to_string()]),
+            None => record.extend([String::new(), String::new()]),
+        }
+        if let Some(player_metadata) = player_metadata {
+            let metadata = player_metadata
+                .get(&(result.player_id, *result.seasons.start()))
+                .cloned()
+                .unwrap_or_default();
+            record.extend(metadata.to_record());
         }
+        writer.write_record(&record)?;
     }
 
     writer.flush()?;
@@ -198,79 +451,493 @@ pub fn write_correlations_to_csv(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::data_structures::{Player, Team};
+    use crate::data_loader::{load_team_abbreviations, LoadOptions};
+    use crate::data_structures::{
+        Honors, LeagueFilter, OpponentShooting, Player, Qualifier, ShotAttempts, TeamSummary,
+    };
+    use crate::significance::PValueAdjustment;
 
     #[test]
-    fn test_correlate_statistics() {
-        let player1 = Player {
-            id: 1,
-            name: "Player 1".to_string(),
-            team_abbreviation: "TEA".to_string(),
-            season: 2022,
-            fg_percent: 0.5,
-            fg_percent_from_x2p_range: 0.6,
-            fg_percent_from_x3p_range: 0.4,
-        };
-        let team1 = Team {
-            abbreviation: "TEA".to_string(),
-            name: "Team A".to_string(),
-            season: 2022,
-            playoffs: true,
-            fg_percentage: 0.45,
-            two_point_percentage: 0.55,
-            three_point_percentage: 0.35,
-            points_per_game: 100.0,
-        };
+    fn test_shooting_differentials() {
         let merged_data = vec![MergedData {
-            player: player1,
-            team: team1,
+            player: Player::test_fixture(),
+            team: Team {
+                opponent: Some(OpponentShooting { fg_percent: Some(0.47), ..Default::default() }),
+                ..Team::test_fixture()
+            },
+            honors: Honors { all_star: true, mvp_share: Some(0.2), ..Default::default() },
         }];
 
-        let correlation_results = correlate_statistics(&merged_data);
+        let differentials = shooting_differentials(&merged_data, &AnalysisScope::default());
 
-        assert_eq!(correlation_results.len(), 6);
-        assert_eq!(correlation_results[0].statistic_name, "FG Percent Diff");
-        assert_eq!(correlation_results[1].statistic_name, "FG Percent Ratio");
+        assert_eq!(differentials.len(), 3);
+        assert_eq!(differentials[0].statistic_name, "FG Percent");
+        assert_eq!(differentials[0].season, 2022);
+        assert!((differentials[0].difference() - 0.05).abs() < 1e-12);
+        assert!((differentials[0].ratio() - 0.5 / 0.45).abs() < 1e-12);
+        assert!((differentials[0].team_edge.unwrap() + 0.02).abs() < 1e-12);
+        assert_eq!(differentials[1].team_edge, None);
+        assert!(differentials.iter().all(|d| d.all_star && d.mvp_share == Some(0.2)));
     }
 
     #[test]
     fn test_analyze_playoff_correlation() {
-        let player1 = Player {
-            id: 1,
-            name: "Player 1".to_string(),
-            team_abbreviation: "TEA".to_string(),
-            season: 2022,
-            fg_percent: 0.5,
-            fg_percent_from_x2p_range: 0.6,
-            fg_percent_from_x3p_range: 0.4,
+        let merged_data =
+            vec![MergedData { player: Player::test_fixture(), team: Team::test_fixture(), honors: Honors::default() }];
+
+        let playoff_correlation_results =
+            analyze_playoff_correlation(&merged_data, &AnalysisScope::default(), &CorrelationOptions::default());
+
+        let statistic_names: Vec<&str> =
+            playoff_correlation_results.all_players_correlation.iter().map(|r| r.statistic_name.as_str()).collect();
+        assert_eq!(statistic_names, ["FG Percent", "FG Percent from 2P Range", "FG Percent from 3P Range"]);
+    }
+
+    #[test]
+    fn test_missing_statistics_are_skipped() {
+        let player = Player {
+            season: 1975,
+            fg_percent_from_x2p_range: Some(0.5),
+            fg_percent_from_x3p_range: None,
+            ..Player::test_fixture()
         };
-        let team1 = Team {
-            abbreviation: "TEA".to_string(),
-            name: "Team A".to_string(),
-            season: 2022,
-            playoffs: true,
-            fg_percentage: 0.45,
-            two_point_percentage: 0.55,
-            three_point_percentage: 0.35,
-            points_per_game: 100.0,
+        let team = Team {
+            season: 1975,
+            playoffs: false,
+            two_point_percentage: Some(0.45),
+            three_point_percentage: None,
+            ..Team::test_fixture()
         };
-        let merged_data = vec![MergedData {
-            player: player1,
-            team: team1,
+        let merged_data = vec![
+            MergedData { player: player.clone(), team: team.clone(), honors: Honors::default() },
+            MergedData {
+                player: Player { id: 2, fg_percent: Some(0.4), fg_percent_from_x3p_range: Some(0.3), ..player },
+                team: Team { playoffs: true, three_point_percentage: Some(0.35), ..team },
+                honors: Honors::default(),
+            },
+        ];
+
+        let differentials = shooting_differentials(&merged_data, &AnalysisScope::default());
+        assert_eq!(differentials.len(), 5);
+        assert!(differentials[..2].iter().all(|r| !r.statistic_name.contains("3P Range")));
+
+        let playoff_correlation_results =
+            analyze_playoff_correlation(&merged_data, &AnalysisScope::default(), &CorrelationOptions::default());
+        let correlations = &playoff_correlation_results.all_players_correlation;
+        assert!((correlations[0].correlation_coefficient + 1.0).abs() < 1e-9);
+        assert!(correlations[2].correlation_coefficient.is_nan());
+    }
+
+    #[test]
+    fn test_write_correlations_to_csv_with_metadata() {
+        let correlations = vec![CorrelationResult {
+            seasons: 2022..=2022,
+            method: CorrelationMethod::Spearman,
+            player_id: 7,
+            player_name: "Player 7".to_string(),
+            team_name: "Team A".to_string(),
+            statistic_name: "FG Percent vs Win Percentage".to_string(),
+            correlation_coefficient: 0.05,
+            significance: Significance {
+                n: 120,
+                p_value: 0.02,
+                adjustment: PValueAdjustment::Holm,
+                adjusted_p_value: 0.06,
+                significant: false,
+                confidence_interval: (-0.1, 0.2),
+                bootstrap_interval: None,
+            },
         }];
+        let player_metadata = HashMap::from([(
+            (7, 2022),
+            PlayerMetadata {
+                position: Some("SG".to_string()),
+                age: Some(24),
+                experience: Some(2),
+                hall_of_fame: Some(false),
+                ..Default::default()
+            },
+        )]);
+
+        let file_path = "Correlation Metadata Test.csv";
+        write_correlations_to_csv(&correlations, file_path, true, Some(&player_metadata)).unwrap();
+        let file_contents = std::fs::read_to_string(file_path).unwrap();
+        std::fs::remove_file(file_path).unwrap();
+
+        assert_eq!(
+            file_contents,
+            "Season,Player ID,Player Name,Team Name,Statistic Name,Method,Correlation Coefficient,N,P Value,Adjustment,\
+             Adjusted P Value,Significant,CI Low,CI High,Bootstrap CI Low,Bootstrap CI High,Position,Age,Experience,Hall of Fame,\
+             On Court Plus Minus Per 100 Poss,Net Plus Minus Per 100 Poss\n\
+             2022,7,Player 7,Team A,FG Percent vs Win Percentage,Spearman,0.05,120,0.02,Holm,0.06,false,-0.1,0.2,,,SG,24,2,false,,\n"
+        );
+    }
+
+    #[test]
+    fn test_write_differentials_to_csv() {
+        let differentials = vec![ShootingDifferential {
+            season: 2022,
+            player_id: 7,
+            player_name: "Player 7".to_string(),
+            team_name: "Team A".to_string(),
+            statistic_name: "FG Percent".to_string(),
+            player_value: 0.5,
+            team_value: 0.4,
+            team_edge: Some(-0.02),
+            all_star: true,
+            all_nba_team: None,
+            mvp_share: Some(0.1),
+        }];
+
+        let file_path = "Differentials Test.csv";
+        write_differentials_to_csv(&differentials, file_path, None).unwrap();
+        let file_contents = std::fs::read_to_string(file_path).unwrap();
+        std::fs::remove_file(file_path).unwrap();
+
+        assert_eq!(
+            file_contents,
+            "Season,Player ID,Player Name,Team Name,Statistic Name,Player Value,Team Value,Difference,Ratio,\
+             Team Edge,All-Star,All-NBA Team,MVP Share\n\
+             2022,7,Player 7,Team A,FG Percent,0.5,0.4,0.09999999999999998,1.25,-0.02,true,,0.1\n"
+        );
+    }
+
+    #[test]
+    fn test_correlate_player_careers() {
+        let player =
+            Player { fg_percent_from_x2p_range: None, fg_percent_from_x3p_range: None, ..Player::test_fixture() };
+        let team =
+            Team { playoffs: false, two_point_percentage: None, three_point_percentage: None, ..Team::test_fixture() };
+        // Player 1 shoots better every season and the team scores more; player 2 only has
+        // two seasons.
+        let season = |id: u32, season: u32, fg_percent: f64, points: f64| MergedData {
+            player: Player { id, season, fg_percent: Some(fg_percent), ..player.clone() },
+            team: Team { season, points_per_game: Some(points), ..team.clone() },
+            honors: Honors::default(),
+        };
+        let merged_data = vec![
+            season(1, 2020, 0.40, 100.0),
+            season(1, 2021, 0.45, 105.0),
+            season(1, 2022, 0.50, 110.0),
+            season(2, 2021, 0.45, 105.0),
+            season(2, 2022, 0.50, 110.0),
+        ];
+
+        let scope = AnalysisScope { seasons: 2022..=2022, ..Default::default() };
+        let results = correlate_player_careers(
+            &merged_data,
+            &scope,
+            &CorrelationOptions::default(),
+            &FranchiseRegistry::default(),
+        );
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].player_id, 1);
+        assert_eq!(results[0].team_name, "Team A");
+        assert_eq!(results[0].seasons, 2020..=2022);
+        assert_eq!(results[0].statistic_name, "FG Percent vs Points Per Game");
+        assert!((results[0].correlation_coefficient - 1.0).abs() < 1e-9);
+        assert_eq!(results[0].significance.n, 3);
+        assert_eq!(results[0].significance.p_value, 0.0);
+
+        let mut results = results;
+        adjust_p_values(&mut results, &CorrelationOptions { adjustment: PValueAdjustment::Holm, ..Default::default() });
+        assert_eq!(results[0].significance.adjustment, PValueAdjustment::Holm);
+        assert!(results[0].significance.significant);
+
+        let later_scope = AnalysisScope { seasons: 2023..=2024, ..Default::default() };
+        assert!(correlate_player_careers(
+            &merged_data,
+            &later_scope,
+            &CorrelationOptions::default(),
+            &FranchiseRegistry::default()
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_career_seasons_use_their_own_qualifier() {
+        let season = |season: u32, fg_made: f64, fg_percent: f64, points: f64| MergedData {
+            player: Player {
+                season,
+                games: Some(80),
+                minutes: Some(2800),
+                fg_percent: Some(fg_percent),
+                fg_percent_from_x2p_range: None,
+                fg_percent_from_x3p_range: None,
+                attempts: ShotAttempts { fga: Some(fg_made / fg_percent), ..Default::default() },
+                ..Player::test_fixture()
+            },
+            team: Team { season, points_per_game: Some(points), ..Team::test_fixture() },
+            honors: Honors::default(),
+        };
+        // 250 made field goals in the 66 game 2012 season is enough for that season only.
+        let merged_data = vec![
+            season(2010, 320.0, 0.40, 100.0),
+            season(2011, 320.0, 0.45, 104.0),
+            season(2012, 250.0, 0.50, 103.0),
+            season(2013, 320.0, 0.55, 110.0),
+        ];
+        let career_n = |scope: &AnalysisScope| {
+            let results = correlate_player_careers(
+                &merged_data,
+                scope,
+                &CorrelationOptions::default(),
+                &FranchiseRegistry::default(),
+            );
+            results.iter().find(|r| r.statistic_name == "FG Percent vs Points Per Game").map(|r| r.significance.n)
+        };
 
-        let playoff_correlation_results = analyze_playoff_correlation(&merged_data);
+        let per_season = AnalysisScope {
+            season_qualifiers: (2010..=2013)
+                .map(|s| (s, Qualifier::basketball_reference(if s == 2012 { 66 } else { 82 })))
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(per_season.qualifier(2012).min_fg_made, Some(242.0));
+        assert_eq!(per_season.qualifier(2014), &Qualifier::default());
+        assert_eq!(career_n(&per_season), Some(4));
+        assert_eq!(career_n(&AnalysisScope { seasons: 2013..=2013, ..per_season }), Some(4));
+
+        let full_length = AnalysisScope { qualifier: Qualifier::basketball_reference(82), ..Default::default() };
+        assert_eq!(career_n(&full_length), Some(3));
+        assert_eq!(career_n(&AnalysisScope::default()), Some(4));
+    }
 
-        assert_eq!(playoff_correlation_results.all_players_correlation.len(), 3);
+    #[test]
+    fn test_career_follows_relocated_franchise() {
+        let (team_abbreviations, _) =
+            load_team_abbreviations("NBA Stats (1947-Present)/Team Abbrev.csv", &LoadOptions::default()).unwrap();
+        let franchises = FranchiseRegistry::new(team_abbreviations);
+        let season = |season: u32, abbreviation: &str, name: &str, fg_percent: f64, points: f64| MergedData {
+            player: Player {
+                season,
+                team_abbreviation: abbreviation.to_string(),
+                fg_percent: Some(fg_percent),
+                ..Player::test_fixture()
+            },
+            team: Team {
+                season,
+                abbreviation: abbreviation.to_string(),
+                name: name.to_string(),
+                points_per_game: Some(points),
+                ..Team::test_fixture()
+            },
+            honors: Honors::default(),
+        };
+        let merged_data = vec![
+            season(2010, "OKC", "Oklahoma City Thunder", 0.47, 104.0),
+            season(2008, "SEA", "Seattle SuperSonics", 0.43, 97.0),
+            season(2011, "HOU", "Houston Rockets", 0.45, 101.0),
+            season(2009, "OKC", "Oklahoma City Thunder", 0.45, 100.0),
+        ];
+
+        let scope = AnalysisScope { seasons: 2011..=2011, ..Default::default() };
+        let results = correlate_player_careers(&merged_data, &scope, &CorrelationOptions::default(), &franchises);
+        assert_eq!(results[0].seasons, 2008..=2011);
+        assert_eq!(results[0].team_name, "Oklahoma City Thunder, Houston Rockets");
+    }
+
+    #[test]
+    fn test_analyze_outcome_correlation() {
+        let player = Player { fg_percent_from_x2p_range: Some(0.5), ..Player::test_fixture() };
+        let summary = TeamSummary {
+            season: 2022,
+            lg: "NBA".to_string(),
+            name: "Team A".to_string(),
+            abbreviation: "TEA".to_string(),
+            playoffs: true,
+            wins: Some(60),
+            losses: Some(22),
+            n_rtg: Some(8.0),
+            ..Default::default()
+        };
+        let team = Team { summary: Some(summary.clone()), ..Team::test_fixture() };
+        let merged_data = vec![
+            MergedData { player: player.clone(), team: team.clone(), honors: Honors::default() },
+            MergedData {
+                player: Player { id: 2, fg_percent: Some(0.4), ..player.clone() },
+                team: Team {
+                    summary: Some(TeamSummary { wins: Some(30), losses: Some(52), n_rtg: Some(-4.0), ..summary }),
+                    ..team.clone()
+                },
+                honors: Honors::default(),
+            },
+            MergedData {
+                player: Player { id: 3, fg_percent: Some(0.1), ..player },
+                team: Team { summary: None, ..team },
+                honors: Honors::default(),
+            },
+        ];
+
+        let wins = analyze_outcome_correlation(
+            &merged_data,
+            TeamOutcome::WinPercentage,
+            &AnalysisScope::default(),
+            &CorrelationOptions::default(),
+        );
+        let fg_vs_wins = &wins.all_players_correlation[0];
+        assert_eq!(fg_vs_wins.statistic_name, "FG Percent vs Win Percentage");
+        assert!((fg_vs_wins.correlation_coefficient - 1.0).abs() < 1e-9);
+        assert_eq!(fg_vs_wins.significance.n, 2);
+        assert!(fg_vs_wins.significance.p_value.is_nan());
+
+        let net_rating = analyze_outcome_correlation(
+            &merged_data,
+            TeamOutcome::NetRating,
+            &AnalysisScope::default(),
+            &CorrelationOptions::default(),
+        );
+        assert_eq!(net_rating.all_players_correlation[0].statistic_name, "FG Percent vs Net Rating");
+        assert!(net_rating.all_players_correlation[2].correlation_coefficient.is_nan());
+
+        let playoffs =
+            analyze_playoff_correlation(&merged_data, &AnalysisScope::default(), &CorrelationOptions::default());
+        assert_eq!(playoffs.all_players_correlation[0].statistic_name, "FG Percent");
+
+        let kendall = CorrelationOptions { method: CorrelationMethod::KendallTau, ..Default::default() };
+        let ranked_wins =
+            analyze_outcome_correlation(&merged_data, TeamOutcome::WinPercentage, &AnalysisScope::default(), &kendall);
+        assert_eq!(ranked_wins.all_players_correlation[0].method, CorrelationMethod::KendallTau);
+        assert_eq!(ranked_wins.all_players_correlation[0].correlation_coefficient, 1.0);
     }
 
     #[test]
-    fn test_calculate_correlation() {
-        let x_values = vec![1.0, 2.0, 3.0, 4.0, 5.0];
-        let y_values = vec![2.0, 4.0, 6.0, 8.0, 10.0];
+    fn test_multi_team_policies_differ() {
+        let row = |id: u32, abbreviation: &str, points: f64, fg_percent: f64, stint_weight: f64| MergedData {
+            player: Player {
+                id,
+                team_abbreviation: abbreviation.to_string(),
+                fg_percent: Some(fg_percent),
+                stint_weight,
+                ..Player::test_fixture()
+            },
+            team: Team {
+                abbreviation: abbreviation.to_string(),
+                points_per_game: Some(points),
+                ..Team::test_fixture()
+            },
+            honors: Honors::default(),
+        };
+        let others = vec![
+            row(1, "TEA", 110.0, 0.50, 1.0),
+            row(2, "TEB", 100.0, 0.42, 1.0),
+            row(3, "TEC", 105.0, 0.47, 1.0),
+            row(4, "TEA", 110.0, 0.48, 1.0),
+            row(5, "TEB", 100.0, 0.45, 1.0),
+        ];
+        // Player 9 shot 40% over 900 minutes for TEA and 56% over 300 for TEB, 44% in all.
+        let total_only = [others.clone(), vec![row(9, "TEA", 110.0, 0.44, 1.0)]].concat();
+        let stints_only =
+            [others.clone(), vec![row(9, "TEA", 110.0, 0.40, 1.0), row(9, "TEB", 100.0, 0.56, 1.0)]].concat();
+        let minutes_weighted =
+            [others, vec![row(9, "TEA", 110.0, 0.40, 0.75), row(9, "TEB", 100.0, 0.56, 0.25)]].concat();
+
+        let correlation = |merged_data: &[MergedData]| {
+            let results = analyze_outcome_correlation(
+                merged_data,
+                TeamOutcome::Points(StatBasis::PerGame),
+                &AnalysisScope::default(),
+                &CorrelationOptions::default(),
+            );
+            results.all_players_correlation[0].clone()
+        };
+        let (total, stints, weighted) =
+            (correlation(&total_only), correlation(&stints_only), correlation(&minutes_weighted));
+
+        assert!((total.correlation_coefficient - stints.correlation_coefficient).abs() > 1e-3);
+        assert!((stints.correlation_coefficient - weighted.correlation_coefficient).abs() > 1e-3);
+        assert!((total.correlation_coefficient - weighted.correlation_coefficient).abs() > 1e-3);
+        assert_eq!((stints.significance.n, weighted.significance.n), (7, 7));
+        assert!(weighted.significance.p_value != stints.significance.p_value);
+    }
 
-        let correlation = calculate_correlation(&x_values, &y_values);
+    #[test]
+    fn test_league_filter() {
+        let player = Player {
+            season: 1975,
+            fg_percent_from_x2p_range: Some(0.5),
+            fg_percent_from_x3p_range: None,
+            ..Player::test_fixture()
+        };
+        let team = Team {
+            season: 1975,
+            two_point_percentage: Some(0.5),
+            three_point_percentage: None,
+            ..Team::test_fixture()
+        };
+        let merged_data = vec![
+            MergedData { player: player.clone(), team: team.clone(), honors: Honors::default() },
+            MergedData {
+                player: Player { id: 2, lg: "ABA".to_string(), ..player },
+                team: Team { lg: "ABA".to_string(), ..team },
+                honors: Honors::default(),
+            },
+        ];
+
+        assert_eq!(shooting_differentials(&merged_data, &AnalysisScope::default()).len(), 4);
+        let nba_only = shooting_differentials(
+            &merged_data,
+            &AnalysisScope { leagues: LeagueFilter::only(&["NBA"]), ..Default::default() },
+        );
+        assert_eq!(nba_only.len(), 2);
+        assert!(nba_only.iter().all(|r| r.player_id == 1));
+        assert!(shooting_differentials(
+            &merged_data,
+            &AnalysisScope { leagues: LeagueFilter::only(&["BAA"]), ..Default::default() }
+        )
+        .is_empty());
+        let later_seasons = AnalysisScope { seasons: 1976..=2022, ..Default::default() };
+        assert!(shooting_differentials(&merged_data, &later_seasons).is_empty());
+        let seventies = AnalysisScope { seasons: 1970..=1979, ..Default::default() };
+        let playoffs = analyze_playoff_correlation(&merged_data, &seventies, &CorrelationOptions::default());
+        assert!(playoffs.all_players_correlation.iter().all(|r| r.seasons == (1970..=1979)));
+    }
 
-        assert_eq!(correlation, 1.0);
+    #[test]
+    fn test_qualifier() {
+        let starter = Player {
+            name: "Starter".to_string(),
+            games: Some(80),
+            minutes: Some(2800),
+            fg_percent_from_x2p_range: Some(0.55),
+            attempts: ShotAttempts { fga: Some(1200.0), x2pa: Some(700.0), x3pa: Some(500.0) },
+            ..Player::test_fixture()
+        };
+        // Plenty of field goals but only 30 made threes.
+        let big = Player {
+            id: 2,
+            attempts: ShotAttempts { fga: Some(900.0), x2pa: Some(825.0), x3pa: Some(75.0) },
+            ..starter.clone()
+        };
+        let one_shot = Player {
+            id: 3,
+            games: Some(1),
+            minutes: Some(2),
+            fg_percent: Some(1.0),
+            fg_percent_from_x2p_range: Some(1.0),
+            fg_percent_from_x3p_range: None,
+            attempts: ShotAttempts { fga: Some(1.0), x2pa: Some(1.0), x3pa: Some(0.0) },
+            ..starter.clone()
+        };
+        let team = Team { two_point_percentage: Some(0.5), points_per_game: Some(110.0), ..Team::test_fixture() };
+        let merged_data: Vec<MergedData> = [starter, big, one_shot]
+            .into_iter()
+            .map(|player| MergedData { player, team: team.clone(), honors: Honors::default() })
+            .collect();
+
+        let scope = AnalysisScope { qualifier: Qualifier::basketball_reference(82), ..Default::default() };
+        let results = shooting_differentials(&merged_data, &scope);
+        let count = |id: u32| results.iter().filter(|r| r.player_id == id).count();
+        assert_eq!((count(1), count(2), count(3)), (3, 2, 0));
+        assert_eq!(shooting_differentials(&merged_data, &AnalysisScope::default()).len(), 8);
+
+        let minutes = Qualifier { min_minutes: Some(500), ..Default::default() };
+        assert!(minutes.qualifies(&merged_data[0].player));
+        assert!(minutes.fg_percent(&merged_data[2].player).is_none());
+
+        // The lockout season needed 242 made field goals.
+        let lockout = Qualifier::basketball_reference(66);
+        assert_eq!((lockout.min_fg_made, lockout.min_x3p_made), (Some(242.0), Some(66.0)));
+        assert_eq!((lockout.min_games, lockout.min_minutes), (Some(47), Some(1208)));
     }
-}
\ No newline at end of file
+}
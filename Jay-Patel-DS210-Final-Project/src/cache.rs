@@ -0,0 +1,307 @@
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
+use serde::{Deserialize, Serialize};
+use std::any::type_name;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use crate::data_loader::LoadReport;
+
+// Bump when the snapshot layout itself changes. Row structs and their parsers are covered
+// by `schema_fingerprint` in every key, and a snapshot that fails to decode is parsed again.
+const CACHE_VERSION: u32 = 4;
+
+// Written in front of every snapshot. A snapshot is used when the version and key match
+// and the source CSV has the same length and either the same modification time or, when
+// the file was only touched, the same content hash.
+#[derive(Serialize, Deserialize)]
+struct CacheHeader {
+    version: u32,
+    key: String,
+    source_len: u64,
+    source_modified: u128,
+    source_hash: u64,
+}
+
+// FNV-1a, used instead of std's hasher because that one may change between Rust releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// Field names of a struct, read from its Deserialize impl without a value to decode.
+// Empty for anything that isn't a struct.
+fn field_names<T: DeserializeOwned>() -> &'static [&'static str] {
+    struct FieldTracer<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for FieldTracer<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("only the field names are read"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option unit
+            unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldTracer(&mut fields));
+    fields
+}
+
+// Identifies the shape of a snapshot's rows by the row type, its fields, and the CSV
+// columns its parser reads, so changing any of them makes older snapshots a miss.
+pub fn schema_fingerprint<T: DeserializeOwned>(columns_read: &[String]) -> u64 {
+    let mut schema = vec![type_name::<T>()];
+    schema.extend(field_names::<T>());
+    schema.extend(columns_read.iter().map(String::as_str));
+    fnv1a(schema.join(",").as_bytes())
+}
+
+fn modified_nanos(metadata: &fs::Metadata) -> u128 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0)
+}
+
+fn cache_path(cache_dir: &str, file_path: &str, key: &str) -> PathBuf {
+    let name = format!("{:016x}.bin", fnv1a(format!("{}|{}", file_path, key).as_bytes()));
+    Path::new(cache_dir).join(name)
+}
+
+// A snapshot whose source was only touched is written again with the new modification
+// time, so the next load matches on it without hashing the file.
+fn read_snapshot<T: Serialize + DeserializeOwned>(
+    path: &Path,
+    file_path: &str,
+    key: &str,
+    metadata: &fs::Metadata,
+) -> Option<(Vec<T>, LoadReport)> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let header: CacheHeader = bincode::deserialize_from(&mut reader).ok()?;
+    if header.version != CACHE_VERSION || header.key != key || header.source_len != metadata.len() {
+        return None;
+    }
+    let touched = header.source_modified != modified_nanos(metadata);
+    if touched && header.source_hash != fnv1a(&fs::read(file_path).ok()?) {
+        return None;
+    }
+
+    let snapshot: (Vec<T>, LoadReport) = bincode::deserialize_from(&mut reader).ok()?;
+    drop(reader);
+    if touched {
+        if let Err(e) = write_snapshot(path, file_path, key, metadata, &snapshot) {
+            eprintln!("Could not refresh cache for '{}': {}", file_path, e);
+        }
+    }
+
+    let (rows, mut report) = snapshot;
+    report.from_cache = true;
+    Some((rows, report))
+}
+
+fn write_snapshot<T: Serialize>(
+    path: &Path,
+    file_path: &str,
+    key: &str,
+    metadata: &fs::Metadata,
+    snapshot: &(Vec<T>, LoadReport),
+) -> Result<(), Box<dyn Error>> {
+    let header = CacheHeader {
+        version: CACHE_VERSION,
+        key: key.to_string(),
+        source_len: metadata.len(),
+        source_modified: modified_nanos(metadata),
+        source_hash: fnv1a(&fs::read(file_path)?),
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Written next to the snapshot and renamed over it, so a reader never sees half a file.
+    let temp_path = path.with_extension(format!("tmp{}", std::process::id()));
+    let mut writer = BufWriter::new(File::create(&temp_path)?);
+    bincode::serialize_into(&mut writer, &header)?;
+    bincode::serialize_into(&mut writer, snapshot)?;
+    drop(writer);
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+// Returns the snapshot of `file_path` stored under `key` in `cache_dir` when it is still
+// current, otherwise runs `load` and stores its result. Cache problems never fail a load;
+// an unreadable snapshot is parsed again and a failed write is only reported.
+pub fn load_cached<T: Serialize + DeserializeOwned>(
+    cache_dir: &str,
+    file_path: &str,
+    key: &str,
+    load: impl FnOnce() -> Result<(Vec<T>, LoadReport), Box<dyn Error>>,
+) -> Result<(Vec<T>, LoadReport), Box<dyn Error>> {
+    let Ok(metadata) = fs::metadata(file_path) else {
+        return load();
+    };
+    let path = cache_path(cache_dir, file_path, key);
+
+    if let Some(snapshot) = read_snapshot(&path, file_path, key, &metadata) {
+        return Ok(snapshot);
+    }
+
+    let snapshot = load()?;
+    if let Err(e) = write_snapshot(&path, file_path, key, &metadata, &snapshot) {
+        eprintln!("Could not write cache for '{}': {}", file_path, e);
+    }
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_loader::{load_team_data, LoadOptions};
+    use crate::data_structures::{Team, TeamSummary};
+    use std::time::{Duration, SystemTime};
+
+    const TEAMS: &str = "season,lg,team,abbreviation,playoffs,fg_percent,x2p_percent,x3p_percent,pts_per_game\n\
+                         2022,NBA,Team A,TEA,TRUE,0.45,0.55,0.35,110.5\n";
+
+    #[test]
+    fn test_cache_hits_and_invalidates() {
+        let file_path = "Cache Test Teams.csv";
+        let cache_dir = "Cache Test Dir";
+        std::fs::write(file_path, TEAMS).unwrap();
+        let options = LoadOptions {
+            cache_dir: Some(cache_dir.to_string()),
+            ..Default::default()
+        };
+
+        let (_, first_report) = load_team_data(file_path, &options).unwrap();
+        let (cached_teams, cached_report) = load_team_data(file_path, &options).unwrap();
+
+        // Same content written again only changes the modification time.
+        std::fs::write(file_path, TEAMS).unwrap();
+        let (_, rewritten_report) = load_team_data(file_path, &options).unwrap();
+
+        let strict = LoadOptions { strict: true, ..options.clone() };
+        let (_, strict_report) = load_team_data(file_path, &strict).unwrap();
+
+        std::fs::write(file_path, TEAMS.replace("110.5", "99.5")).unwrap();
+        let (changed_teams, changed_report) = load_team_data(file_path, &options).unwrap();
+
+        std::fs::remove_file(file_path).unwrap();
+        std::fs::remove_dir_all(cache_dir).unwrap();
+
+        assert!(!first_report.from_cache);
+        assert!(cached_report.from_cache);
+        assert_eq!(cached_report.rows_kept, 1);
+        assert_eq!(cached_teams[0].points_per_game, Some(110.5));
+        assert!(rewritten_report.from_cache);
+        assert!(!strict_report.from_cache);
+        assert!(!changed_report.from_cache);
+        assert_eq!(changed_teams[0].points_per_game, Some(99.5));
+    }
+
+    #[test]
+    fn test_touched_source_refreshes_modification_time() {
+        let file_path = "Cache Touch Teams.csv";
+        let cache_dir = "Cache Touch Dir";
+        std::fs::write(file_path, TEAMS).unwrap();
+        let options = LoadOptions {
+            cache_dir: Some(cache_dir.to_string()),
+            ..Default::default()
+        };
+        let (_, first_report) = load_team_data(file_path, &options).unwrap();
+
+        let touched = SystemTime::now() + Duration::from_secs(3600);
+        File::options().write(true).open(file_path).unwrap().set_modified(touched).unwrap();
+        let modified = modified_nanos(&fs::metadata(file_path).unwrap());
+        // The first load after the touch matches on the hash and stores the new time, which
+        // the second load then matches on.
+        let (_, hash_report) = load_team_data(file_path, &options).unwrap();
+        let (_, mtime_report) = load_team_data(file_path, &options).unwrap();
+
+        let snapshot = fs::read_dir(cache_dir).unwrap().next().unwrap().unwrap().path();
+        let header: CacheHeader = bincode::deserialize_from(BufReader::new(File::open(snapshot).unwrap())).unwrap();
+        std::fs::remove_file(file_path).unwrap();
+        std::fs::remove_dir_all(cache_dir).unwrap();
+
+        assert!(!first_report.from_cache);
+        assert!(hash_report.from_cache);
+        assert!(mtime_report.from_cache);
+        assert_eq!(header.source_modified, modified);
+        assert_eq!(header.source_hash, fnv1a(TEAMS.as_bytes()));
+    }
+
+    #[test]
+    fn test_undecodable_snapshot_is_parsed_again() {
+        let file_path = "Cache Corrupt Teams.csv";
+        let cache_dir = "Cache Corrupt Dir";
+        std::fs::write(file_path, TEAMS).unwrap();
+        let options = LoadOptions {
+            cache_dir: Some(cache_dir.to_string()),
+            ..Default::default()
+        };
+        load_team_data(file_path, &options).unwrap();
+
+        // The header still matches, but the rows after it are cut short.
+        let snapshot = fs::read_dir(cache_dir).unwrap().next().unwrap().unwrap().path();
+        let bytes = fs::read(&snapshot).unwrap();
+        fs::write(&snapshot, &bytes[..bytes.len() - 16]).unwrap();
+        let (teams, rebuilt_report) = load_team_data(file_path, &options).unwrap();
+        let (_, cached_report) = load_team_data(file_path, &options).unwrap();
+
+        std::fs::remove_file(file_path).unwrap();
+        std::fs::remove_dir_all(cache_dir).unwrap();
+
+        assert!(!rebuilt_report.from_cache);
+        assert_eq!(teams[0].points_per_game, Some(110.5));
+        assert!(cached_report.from_cache);
+    }
+
+    #[test]
+    fn test_schema_fingerprint() {
+        let columns = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        assert!(field_names::<Team>().contains(&"opponent_stats"));
+        assert!(field_names::<u32>().is_empty());
+        assert_eq!(
+            schema_fingerprint::<Team>(&columns(&["season", "lg"])),
+            schema_fingerprint::<Team>(&columns(&["season", "lg"]))
+        );
+        assert_ne!(
+            schema_fingerprint::<Team>(&columns(&["season", "lg"])),
+            schema_fingerprint::<Team>(&columns(&["season", "lg", "pts_per_game"]))
+        );
+        assert_ne!(
+            schema_fingerprint::<Team>(&columns(&["season", "lg"])),
+            schema_fingerprint::<TeamSummary>(&columns(&["season", "lg"]))
+        );
+    }
+
+    #[test]
+    fn test_no_cache_dir_always_parses() {
+        let (_, report) =
+            load_team_data("NBA Stats (1947-Present)/Team Stats Per Game.csv", &LoadOptions::default()).unwrap();
+
+        assert!(!report.from_cache);
+    }
+}
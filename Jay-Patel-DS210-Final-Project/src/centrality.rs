@@ -1,54 +1,82 @@
 use std::error::Error;
 use csv::Writer;
+use petgraph::algo::dijkstra;
+use petgraph::visit::{EdgeRef, Reversed};
+use petgraph::Graph as PetGraph;
 use rustworkx_core::centrality::{betweenness_centrality, closeness_centrality};
 use std::collections::HashMap;
 use crate::graph::Graph;
+use crate::registry::{PlayerMetadata, PLAYER_METADATA_HEADERS};
+
+// Closeness over the edge weights instead of hop counts: the number of other nodes that
+// reach a node over the summed length of their shortest paths to it, like rustworkx's
+// closeness without the Wasserman-Faust scaling. Isolated nodes score 0.
+fn weighted_closeness_centrality(graph: &PetGraph<u32, f64>) -> Vec<f64> {
+    graph
+        .node_indices()
+        .map(|node| {
+            let distances = dijkstra(Reversed(graph), node, None, |edge| *edge.weight());
+            let total: f64 = distances.values().sum();
+            if total > 0.0 {
+                (distances.len() - 1) as f64 / total
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
 
+// Scores are indexed like the graph's nodes, so a player with a node in several seasons
+// gets a row for each. Metadata is looked up by player id and the node's season. The
+// betweenness and closeness columns count hops; the weighted closeness column uses the
+// edge weights, which carry traded players' stint weights.
 pub fn calculate_centrality(
     graph: &Graph,
     node_labels: &HashMap<u32, String>,
+    node_metadata: &HashMap<(u32, u32), PlayerMetadata>,
     file_path: &str,
 ) -> Result<(), Box<dyn Error>> {
-    let betweenness_scores: HashMap<u32, f64> = betweenness_centrality(&graph.graph, true, true, graph.graph.node_count())
-        .into_iter()
-        .flatten()
-        .zip(graph.graph.node_weights())
-        .map(|(score, node_id)| (*node_id, score))
-        .collect();
-
-    let closeness_scores: HashMap<u32, f64> = closeness_centrality(&graph.graph, false)
-        .into_iter()
-        .flatten()
-        .zip(graph.graph.node_weights())
-        .map(|(score, node_id)| (*node_id, score))
-        .collect();
+    let betweenness_scores = betweenness_centrality(&graph.graph, true, true, graph.graph.node_count());
+    let closeness_scores = closeness_centrality(&graph.graph, false);
+    let weighted_closeness_scores = weighted_closeness_centrality(&graph.graph);
 
     let mut writer = Writer::from_path(file_path)?;
-    writer.write_record(&["Node ID", "Label", "Betweenness Centrality", "Closeness Centrality"])?;
-
-    for node_id in graph.graph.node_weights() {
-        let label = node_labels.get(node_id).cloned().unwrap_or_else(|| "Unknown".to_string());
-        let betweenness_score = *betweenness_scores.get(node_id).unwrap_or(&0.0);
-        let closeness_score = *closeness_scores.get(node_id).unwrap_or(&0.0);
-
-        let betweenness_score_str = if betweenness_score != 0.0 {
-            betweenness_score.to_string()
-        } else {
-            "0.0".to_string()
-        };
-
-        let closeness_score_str = if closeness_score != 0.0 {
-            closeness_score.to_string()
-        } else {
-            "0.0".to_string()
-        };
-
-        writer.write_record(&[
+    let mut headers = vec![
+        "Season",
+        "Node ID",
+        "Label",
+        "Betweenness Centrality",
+        "Closeness Centrality",
+        "Weighted Closeness Centrality",
+    ];
+    headers.extend(PLAYER_METADATA_HEADERS);
+    writer.write_record(&headers)?;
+
+    for node in graph.graph.node_indices() {
+        let node_id = graph.graph[node];
+        let season = graph.node_season(node);
+        let label = node_labels.get(&node_id).cloned().unwrap_or_else(|| "Unknown".to_string());
+        let betweenness_score = betweenness_scores.get(node.index()).copied().flatten().unwrap_or(0.0);
+        let closeness_score = closeness_scores.get(node.index()).copied().flatten().unwrap_or(0.0);
+        let weighted_closeness_score = weighted_closeness_scores[node.index()];
+
+        let format_score = |score: f64| if score != 0.0 { score.to_string() } else { "0.0".to_string() };
+
+        let metadata = season
+            .and_then(|season| node_metadata.get(&(node_id, season)))
+            .cloned()
+            .unwrap_or_default();
+
+        let mut record = vec![
+            season.map(|s| s.to_string()).unwrap_or_default(),
             node_id.to_string(),
             label,
-            betweenness_score_str,
-            closeness_score_str,
-        ])?;
+            format_score(betweenness_score),
+            format_score(closeness_score),
+            format_score(weighted_closeness_score),
+        ];
+        record.extend(metadata.to_record());
+        writer.write_record(&record)?;
     }
 
     writer.flush()?;
@@ -65,10 +93,10 @@ mod tests {
     fn test_calculate_centrality() {
         let mut graph = Graph::new();
 
-        let node1 = graph.add_node(1);
-        let node2 = graph.add_node(2);
-        let node3 = graph.add_node(3);
-        let node4 = graph.add_node(4);
+        graph.add_node(1);
+        graph.add_node(2);
+        graph.add_node(3);
+        graph.add_player_node(4, 2022);
 
         graph.add_edge(1, 2, 1.0);
         graph.add_edge(1, 3, 2.0);
@@ -82,15 +110,31 @@ mod tests {
             (4, "Node 4".to_string()),
         ]);
 
-        let result = calculate_centrality(&graph, &node_labels, "Centrality Test.csv");
+        let node_metadata = HashMap::from([(
+            (4, 2022),
+            PlayerMetadata {
+                position: Some("C".to_string()),
+                age: Some(30),
+                experience: Some(8),
+                hall_of_fame: Some(true),
+                net_plus_minus: Some(3.5),
+                ..Default::default()
+            },
+        )]);
+
+        let result = calculate_centrality(&graph, &node_labels, &node_metadata, "Centrality Test.csv");
         assert!(result.is_ok());
         let file_contents = std::fs::read_to_string("Centrality Test.csv").unwrap();
 
-        assert!(file_contents.contains("Node ID,Label,Betweenness Centrality,Closeness Centrality"));
-        assert!(file_contents.contains("1,Node 1,0.25,0.0"));
-        assert!(file_contents.contains("2,Node 2,0.25,1"));
-        assert!(file_contents.contains("3,Node 3,0.41666666666666663,1"));
-        assert!(file_contents.contains("4,Node 4,0.25,0.6"));
+        assert!(file_contents.contains(
+            "Season,Node ID,Label,Betweenness Centrality,Closeness Centrality,Weighted Closeness Centrality,\
+             Position,Age,Experience,Hall of Fame,On Court Plus Minus Per 100 Poss,Net Plus Minus Per 100 Poss"
+        ));
+        assert!(file_contents.contains("\n,1,Node 1,0.25,0.0,0.0,"));
+        assert!(file_contents.contains("\n,2,Node 2,0.25,1,1,"));
+        // Nodes 1 and 2 reach node 3 over lengths 2 and 3.
+        assert!(file_contents.contains("\n,3,Node 3,0.41666666666666663,1,0.4,"));
+        assert!(file_contents.contains(&format!("\n2022,4,Node 4,0.25,0.6,{},C,30,8,true,,3.5\n", 3.0 / 17.0)));
 
         std::fs::remove_file("Centrality Test.csv").unwrap();
     }
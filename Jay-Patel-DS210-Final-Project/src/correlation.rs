@@ -0,0 +1,206 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+// Pearson measures a linear relationship. Spearman and Kendall only use the order of the
+// values, which suits bounded and skewed statistics like shooting percentages.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CorrelationMethod {
+    #[default]
+    Pearson,
+    Spearman,
+    KendallTau,
+}
+
+impl CorrelationMethod {
+    // Pair i counts `weights[i]` times; traded players' stints carry their share of the
+    // season under MultiTeamPolicy::MinutesWeighted and 1.0 otherwise.
+    pub fn correlate(&self, x_values: &[f64], y_values: &[f64], weights: &[f64]) -> f64 {
+        match self {
+            CorrelationMethod::Pearson => pearson(x_values, y_values, weights),
+            CorrelationMethod::Spearman => spearman(x_values, y_values, weights),
+            CorrelationMethod::KendallTau => kendall_tau(x_values, y_values, weights),
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "pearson" => Some(CorrelationMethod::Pearson),
+            "spearman" => Some(CorrelationMethod::Spearman),
+            "kendall" => Some(CorrelationMethod::KendallTau),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CorrelationMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CorrelationMethod::Pearson => "Pearson",
+            CorrelationMethod::Spearman => "Spearman",
+            CorrelationMethod::KendallTau => "Kendall Tau",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Two passes over the values: the weighted means first, then the weighted sums of products
+// of deviations. Summing raw squares loses most of the precision once the values are large
+// next to their spread. NaN when either side has no spread.
+pub fn pearson(x_values: &[f64], y_values: &[f64], weights: &[f64]) -> f64 {
+    let total_weight: f64 = weights.iter().sum();
+    let mean_x = x_values.iter().zip(weights).map(|(x, w)| x * w).sum::<f64>() / total_weight;
+    let mean_y = y_values.iter().zip(weights).map(|(y, w)| y * w).sum::<f64>() / total_weight;
+
+    let (mut sum_xy, mut sum_xx, mut sum_yy) = (0.0, 0.0, 0.0);
+    for ((x, y), w) in x_values.iter().zip(y_values).zip(weights) {
+        let (dx, dy) = (x - mean_x, y - mean_y);
+        sum_xy += w * dx * dy;
+        sum_xx += w * dx * dx;
+        sum_yy += w * dy * dy;
+    }
+
+    let denominator = (sum_xx * sum_yy).sqrt();
+    if denominator != 0.0 {
+        (sum_xy / denominator).clamp(-1.0, 1.0)
+    } else {
+        f64::NAN
+    }
+}
+
+// 1-based ranks; tied values share the average of the ranks they cover.
+fn average_ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &i in &order[start..end] {
+            ranks[i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+// Weighted Pearson on the ranks. The ranks themselves are of the values alone.
+pub fn spearman(x_values: &[f64], y_values: &[f64], weights: &[f64]) -> f64 {
+    pearson(&average_ranks(x_values), &average_ranks(y_values), weights)
+}
+
+// Kendall's tau-b, which corrects for pairs tied in either variable. Each pair of values
+// counts the product of their weights. Compares every pair, so it is quadratic in the
+// number of values.
+pub fn kendall_tau(x_values: &[f64], y_values: &[f64], weights: &[f64]) -> f64 {
+    let n = x_values.len().min(y_values.len()).min(weights.len());
+    let (mut concordant, mut discordant, mut ties_x, mut ties_y, mut pairs) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let weight = weights[i] * weights[j];
+            pairs += weight;
+            let dx = x_values[i].total_cmp(&x_values[j]);
+            let dy = y_values[i].total_cmp(&y_values[j]);
+            match (dx, dy) {
+                (Ordering::Equal, Ordering::Equal) => {
+                    ties_x += weight;
+                    ties_y += weight;
+                }
+                (Ordering::Equal, _) => ties_x += weight,
+                (_, Ordering::Equal) => ties_y += weight,
+                _ if dx == dy => concordant += weight,
+                _ => discordant += weight,
+            }
+        }
+    }
+
+    let denominator = ((pairs - ties_x) * (pairs - ties_y)).sqrt();
+    if denominator > 0.0 {
+        (concordant - discordant) / denominator
+    } else {
+        f64::NAN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ones(n: usize) -> Vec<f64> {
+        vec![1.0; n]
+    }
+
+    #[test]
+    fn test_pearson() {
+        let x_values = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        let y_values = vec![2.0, 4.0, 6.0, 8.0, 10.0];
+
+        assert_eq!(pearson(&x_values, &y_values, &ones(x_values.len())), 1.0);
+        assert!(pearson(&x_values, &[3.0; 5], &ones(5)).is_nan());
+        assert!(pearson(&[], &[], &[]).is_nan());
+    }
+
+    #[test]
+    fn test_pearson_large_offset() {
+        // The sum of squares formula cancels to noise here.
+        let x_values: Vec<f64> = (0..1000).map(|i| 1e9 + i as f64 * 1e-3).collect();
+        let y_values: Vec<f64> = x_values.iter().map(|x| 2.0 * x).collect();
+
+        assert!((pearson(&x_values, &y_values, &ones(x_values.len())) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_weights_count_pairs() {
+        // Weighting a pair by 2 is the same as listing it twice.
+        let (x_values, y_values) = ([1.0, 2.0, 3.0, 4.0], [1.0, 3.0, 2.0, 4.0]);
+        let weights = [1.0, 2.0, 1.0, 1.0];
+        let (repeated_x, repeated_y) = ([1.0, 2.0, 2.0, 3.0, 4.0], [1.0, 3.0, 3.0, 2.0, 4.0]);
+
+        let weighted = pearson(&x_values, &y_values, &weights);
+        assert!((weighted - pearson(&repeated_x, &repeated_y, &ones(5))).abs() < 1e-12);
+        assert!((weighted - pearson(&x_values, &y_values, &ones(4))).abs() > 1e-3);
+        // 7 concordant and 2 discordant pair weights out of 9, against 5 and 1 out of 6.
+        assert!((kendall_tau(&x_values, &y_values, &weights) - 5.0 / 9.0).abs() < 1e-12);
+        assert!((kendall_tau(&x_values, &y_values, &ones(4)) - 2.0 / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_average_ranks() {
+        assert_eq!(average_ranks(&[10.0, 30.0, 20.0, 30.0]), vec![1.0, 3.5, 2.0, 3.5]);
+    }
+
+    #[test]
+    fn test_spearman_with_ties() {
+        let rho = spearman(&[1.0, 2.0, 3.0, 4.0, 5.0], &[5.0, 6.0, 7.0, 8.0, 7.0], &ones(5));
+
+        assert!((rho - 8.0 / 95f64.sqrt()).abs() < 1e-12);
+        // Monotonic but not linear.
+        assert_eq!(spearman(&[1.0, 2.0, 3.0], &[1.0, 10.0, 1000.0], &ones(3)), 1.0);
+    }
+
+    #[test]
+    fn test_kendall_tau() {
+        assert!((kendall_tau(&[1.0, 2.0, 3.0, 4.0, 5.0], &[3.0, 1.0, 2.0, 5.0, 4.0], &ones(5)) - 0.4).abs() < 1e-12);
+        // One pair tied in each variable: tau-b = 8 / sqrt(9 * 9).
+        let tau_b = kendall_tau(&[1.0, 2.0, 2.0, 3.0, 4.0], &[1.0, 3.0, 2.0, 3.0, 5.0], &ones(5));
+        assert!((tau_b - 8.0 / 9.0).abs() < 1e-12);
+        assert!(kendall_tau(&[1.0, 1.0], &[1.0, 2.0], &ones(2)).is_nan());
+    }
+
+    #[test]
+    fn test_method() {
+        let x_values = [1.0, 2.0, 3.0, 4.0];
+        let y_values = [1.0, 4.0, 9.0, 16.0];
+
+        assert!(CorrelationMethod::Pearson.correlate(&x_values, &y_values, &ones(4)) < 1.0);
+        assert_eq!(CorrelationMethod::Spearman.correlate(&x_values, &y_values, &ones(4)), 1.0);
+        assert_eq!(CorrelationMethod::KendallTau.correlate(&x_values, &y_values, &ones(4)), 1.0);
+        assert_eq!(CorrelationMethod::parse("Kendall"), Some(CorrelationMethod::KendallTau));
+        assert_eq!(CorrelationMethod::parse("rank"), None);
+        assert_eq!(CorrelationMethod::KendallTau.to_string(), "Kendall Tau");
+    }
+}
@@ -1,136 +1,1854 @@
-use csv::Reader;
+use csv::{Reader, StringRecord};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::any::type_name;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
-use crate::data_structures::{Player, Team};
+use std::fmt;
+use std::fs::File;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use crate::cache;
+use crate::data_structures::{
+    AllStarSelection, AwardShare, EndOfSeasonTeam, EndOfSeasonTeamVote, LeagueFilter, OpponentShooting, PlayByPlay, Player,
+    PlayerCareer, PlayerPer100, PlayerSeasonInfo, PlayerTotals, ShotAttempts, ShotProfile, StatBasis, Team, TeamAbbreviation,
+    TeamBoxScore, TeamSummary,
+};
 
-pub fn load_player_data(file_path: &str) -> Result<Vec<Player>, Box<dyn Error>> {
-    let mut reader = Reader::from_path(file_path)?;
-    let mut players = Vec::new();
+// Number of offending values kept per column in a LoadReport.
+const MAX_FAILURE_SAMPLES: usize = 5;
+
+#[derive(Debug)]
+pub struct MissingColumnError {
+    pub file_path: String,
+    pub column: String,
+}
+
+impl fmt::Display for MissingColumnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Missing column '{}' in '{}'", self.column, self.file_path)
+    }
+}
+
+impl Error for MissingColumnError {}
+
+#[derive(Debug)]
+pub struct ParseFailureError {
+    pub file_path: String,
+    pub line: u64,
+    pub column: String,
+    pub value: String,
+}
+
+impl fmt::Display for ParseFailureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid value '{}' for column '{}' on line {} of '{}'",
+            self.value, self.column, self.line, self.file_path
+        )
+    }
+}
+
+impl Error for ParseFailureError {}
+
+#[derive(Clone, Debug, Default)]
+pub struct LoadOptions {
+    // Turns the first unparseable value into an error instead of counting it.
+    pub strict: bool,
+    pub multi_team_policy: MultiTeamPolicy,
+    pub filter: RowFilter,
+    // Directory for parsed snapshots of each file; None parses every time.
+    pub cache_dir: Option<String>,
+}
 
-    reader.records().next();
+// Filters checked against the raw columns before a row is parsed, so skipped rows cost
+// almost nothing. Each one only applies to files that have its column: "season", "lg",
+// "tm" or "abbreviation", and "mp". For player data the team and minutes filters wait
+// until the multi-team policy has resolved each player season instead, see
+// `resolve_player_season`.
+#[derive(Clone, Debug, Default)]
+pub struct RowFilter {
+    pub seasons: Option<RangeInclusive<u32>>,
+    pub leagues: LeagueFilter,
+    // Team abbreviations to keep; empty keeps every team.
+    pub teams: Vec<String>,
+    pub min_minutes: Option<u32>,
+}
 
-    for result in reader.records() {
-        let record = result?;
+impl RowFilter {
+    // The filters that can't split a traded player's rows, as they hold for the TOT row
+    // and every stint alike.
+    fn season_and_league(&self) -> RowFilter {
+        RowFilter {
+            seasons: self.seasons.clone(),
+            leagues: self.leagues.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn keeps_team(&self, abbreviation: &str) -> bool {
+        self.teams.is_empty() || self.teams.iter().any(|t| t == abbreviation)
+    }
+}
+
+// Positions of the filtered columns in one file.
+struct FilterColumns {
+    season: Option<usize>,
+    lg: Option<usize>,
+    team: Option<usize>,
+    minutes: Option<usize>,
+}
 
-        let player = Player {
-            id: match record[2].parse() {
-                Ok(id) => id,
-                Err(e) => {
-                    eprintln!("Error parsing player ID '{}': {:?}", record[2].to_string(), e);
-                    continue;
+impl FilterColumns {
+    fn new(columns: &Columns, filter: &RowFilter) -> Self {
+        FilterColumns {
+            season: columns.find("season").filter(|_| filter.seasons.is_some()),
+            lg: columns.find("lg").filter(|_| !filter.leagues.is_all()),
+            team: columns
+                .find("tm")
+                .or_else(|| columns.find("abbreviation"))
+                .filter(|_| !filter.teams.is_empty()),
+            minutes: columns.find("mp").filter(|_| filter.min_minutes.is_some()),
+        }
+    }
+
+    // The reason a record is skipped, if it is. Seasons that don't parse are left for the
+    // row parser to report; minutes that don't parse can't meet a minimum.
+    fn skip_reason(&self, record: &StringRecord, filter: &RowFilter) -> Option<&'static str> {
+        if let (Some(index), Some(seasons)) = (self.season, &filter.seasons) {
+            if let Ok(season) = record[index].trim().parse::<u32>() {
+                if !seasons.contains(&season) {
+                    return Some("outside season range");
                 }
-            },
-            name: record[3].to_string(),
-            team_abbreviation: record[9].to_string(),
-            season: record[1].parse()?,
-            fg_percent: if &record[12] == "NA" {
-                0.0
-            } else {
-                record[12].parse().unwrap_or_else(|_| {
-                    eprintln!("Error parsing FG percent '{}': Invalid value", record[12].to_string());
-                    0.0
-                })
-            },
-            fg_percent_from_x2p_range: if &record[20] == "NA" {
-                0.0
-            } else {
-                record[20].parse().unwrap_or_else(|_| {
-                    eprintln!("Error parsing FG percent from 2P range '{}': Invalid value", record[20].to_string());
-                    0.0
-                })
-            },
-            fg_percent_from_x3p_range: if &record[25] == "NA" {
-                0.0
-            } else {
-                record[25].parse().unwrap_or_else(|_| {
-                    eprintln!("Error parsing FG percent from 3P range '{}': Invalid value", record[25].to_string());
-                    0.0
-                })
-            },
+            }
+        }
+        if let Some(index) = self.lg {
+            if !filter.leagues.matches(&record[index]) {
+                return Some("other league");
+            }
+        }
+        if let Some(index) = self.team {
+            if !filter.keeps_team(&record[index]) {
+                return Some("other team");
+            }
+        }
+        if let (Some(index), Some(min_minutes)) = (self.minutes, filter.min_minutes) {
+            if record[index].trim().parse::<u32>().map_or(true, |mp| mp < min_minutes) {
+                return Some("below minimum minutes");
+            }
+        }
+        None
+    }
+}
+
+// Players traded mid-season have a "TOT" row with their combined season plus one row per
+// team stint. The policy decides which of those rows the player loader keeps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MultiTeamPolicy {
+    // Keep only the combined row, credited to the team the player logged the most minutes for.
+    TotalOnly,
+    // Keep only the per-team stints, each counted as a full player season.
+    #[default]
+    StintsOnly,
+    // Keep the per-team stints, weighted by their share of the player's minutes.
+    MinutesWeighted,
+}
+
+impl MultiTeamPolicy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "total" => Some(MultiTeamPolicy::TotalOnly),
+            "stints" => Some(MultiTeamPolicy::StintsOnly),
+            "weighted" => Some(MultiTeamPolicy::MinutesWeighted),
+            _ => None,
+        }
+    }
+}
+
+const MULTI_TEAM_TOTAL: &str = "TOT";
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ColumnFailures {
+    pub count: usize,
+    pub samples: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LoadReport {
+    pub file_path: String,
+    pub rows_read: usize,
+    pub rows_kept: usize,
+    pub rows_dropped: HashMap<String, usize>,
+    pub parse_failures: HashMap<String, ColumnFailures>,
+    // Set when the rows came from a cached snapshot instead of the CSV.
+    #[serde(skip)]
+    pub from_cache: bool,
+}
+
+impl LoadReport {
+    fn new(file_path: &str) -> Self {
+        LoadReport {
+            file_path: file_path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn total_dropped(&self) -> usize {
+        self.rows_dropped.values().sum()
+    }
+
+    fn drop_row(&mut self, reason: &str) {
+        *self.rows_dropped.entry(reason.to_string()).or_default() += 1;
+    }
+
+    fn record_failure(&mut self, column: &str, value: &str) {
+        let failures = self.parse_failures.entry(column.to_string()).or_default();
+        failures.count += 1;
+        if failures.samples.len() < MAX_FAILURE_SAMPLES && !failures.samples.iter().any(|s| s == value) {
+            failures.samples.push(value.to_string());
+        }
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} rows read, {} kept, {} dropped",
+            self.file_path,
+            self.rows_read,
+            self.rows_kept,
+            self.total_dropped()
+        )?;
+        if self.from_cache {
+            write!(f, " (cached)")?;
+        }
+
+        let mut reasons: Vec<_> = self.rows_dropped.iter().collect();
+        reasons.sort();
+        for (reason, count) in reasons {
+            write!(f, "\n  dropped {} rows: {}", count, reason)?;
+        }
+
+        let mut columns: Vec<_> = self.parse_failures.iter().collect();
+        columns.sort_by(|a, b| a.0.cmp(b.0));
+        for (column, failures) in columns {
+            write!(
+                f,
+                "\n  {} unparseable values in '{}' (e.g. {})",
+                failures.count,
+                column,
+                failures.samples.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+// Maps header names to their position so loaders never depend on column order.
+struct Columns {
+    file_path: String,
+    indexes: HashMap<String, usize>,
+    // Every column looked up, when set.
+    read: Option<RefCell<Vec<String>>>,
+}
+
+impl Columns {
+    fn from_reader(reader: &mut Reader<File>, file_path: &str) -> Result<Self, Box<dyn Error>> {
+        let indexes = reader
+            .headers()?
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.trim().to_string(), i))
+            .collect();
+
+        Ok(Columns {
+            file_path: file_path.to_string(),
+            indexes,
+            read: None,
+        })
+    }
+
+    fn find(&self, column: &str) -> Option<usize> {
+        self.indexes.get(column).copied()
+    }
+
+    fn index(&self, column: &str) -> Result<usize, MissingColumnError> {
+        if let Some(read) = &self.read {
+            read.borrow_mut().push(column.to_string());
+        }
+        self.indexes.get(column).copied().ok_or_else(|| MissingColumnError {
+            file_path: self.file_path.clone(),
+            column: column.to_string(),
+        })
+    }
+}
+
+// A record being parsed. Failures are counted in the report as they happen; a failed
+// required value marks the row invalid so `load_rows` drops it.
+struct Row<'a> {
+    record: &'a StringRecord,
+    columns: &'a Columns,
+    report: &'a mut LoadReport,
+    options: &'a LoadOptions,
+    invalid_column: Option<String>,
+}
+
+impl<'a> Row<'a> {
+    fn value(&self, column: &str) -> Result<&'a str, MissingColumnError> {
+        let index = self.columns.index(column)?;
+        Ok(&self.record[index])
+    }
+
+    fn text(&self, column: &str) -> Result<String, Box<dyn Error>> {
+        Ok(self.value(column)?.to_string())
+    }
+
+    fn parse<T>(&mut self, column: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Option<T>, Box<dyn Error>> {
+        let value = self.value(column)?;
+        if let Some(parsed) = parse(value) {
+            return Ok(Some(parsed));
+        }
+
+        if self.options.strict {
+            return Err(Box::new(ParseFailureError {
+                file_path: self.report.file_path.clone(),
+                line: self.record.position().map_or(0, |p| p.line()),
+                column: column.to_string(),
+                value: value.to_string(),
+            }));
+        }
+
+        self.report.record_failure(column, value);
+        Ok(None)
+    }
+
+    fn invalidate(&mut self, column: &str) {
+        if self.invalid_column.is_none() {
+            self.invalid_column = Some(column.to_string());
+        }
+    }
+
+    // A value that must be present; "NA" counts as a failure.
+    fn required<T: FromStr + Default>(&mut self, column: &str) -> Result<T, Box<dyn Error>> {
+        match self.parse(column, |v| v.trim().parse().ok())? {
+            Some(value) => Ok(value),
+            None => {
+                self.invalidate(column);
+                Ok(T::default())
+            }
+        }
+    }
+
+    fn flag(&mut self, column: &str) -> Result<bool, Box<dyn Error>> {
+        match self.parse(column, parse_bool)? {
+            Some(value) => Ok(value),
+            None => {
+                self.invalidate(column);
+                Ok(false)
+            }
+        }
+    }
+
+    fn optional_flag(&mut self, column: &str) -> Result<Option<bool>, Box<dyn Error>> {
+        let parse = |v: &str| match v.trim() {
+            "NA" | "" => Some(None),
+            v => parse_bool(v).map(Some),
         };
+        Ok(self.parse(column, parse)?.flatten())
+    }
 
-        players.push(player);
+    // "NA" becomes None, but an unparseable value drops the row.
+    fn nullable<T: FromStr>(&mut self, column: &str) -> Result<Option<T>, Box<dyn Error>> {
+        match self.parse(column, |v| parse_optional(v).ok())? {
+            Some(value) => Ok(value),
+            None => {
+                self.invalidate(column);
+                Ok(None)
+            }
+        }
     }
 
-    Ok(players)
+    // "NA" and unparseable values both become None and the row is kept.
+    fn optional<T: FromStr>(&mut self, column: &str) -> Result<Option<T>, Box<dyn Error>> {
+        Ok(self.parse(column, |v| parse_optional(v).ok())?.flatten())
+    }
 }
 
-pub fn load_team_data(file_path: &str) -> Result<Vec<Team>, Box<dyn Error>> {
+// Basketball-Reference marks stats that weren't tracked (e.g. 3P% before 1980) as "NA".
+fn parse_optional<T: FromStr>(value: &str) -> Result<Option<T>, T::Err> {
+    match value.trim() {
+        "NA" | "" => Ok(None),
+        value => value.parse().map(Some),
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+// Goes through the cache when one is configured. The key holds the schema of the rows and
+// every option that changes which rows are parsed, so each combination gets its own
+// snapshot.
+fn load_rows<T: Serialize + DeserializeOwned>(
+    file_path: &str,
+    options: &LoadOptions,
+    mut parse_row: impl FnMut(&mut Row) -> Result<T, Box<dyn Error>>,
+) -> Result<(Vec<T>, LoadReport), Box<dyn Error>> {
+    match &options.cache_dir {
+        Some(cache_dir) => {
+            let schema = cache::schema_fingerprint::<T>(&columns_read(file_path, &mut parse_row)?);
+            let key = format!("{}|{:016x}|strict={}|{:?}", type_name::<T>(), schema, options.strict, options.filter);
+            cache::load_cached(cache_dir, file_path, &key, || parse_rows(file_path, options, parse_row))
+        }
+        None => parse_rows(file_path, options, parse_row),
+    }
+}
+
+// The columns `parse_row` reads, found by parsing the first record with a throwaway report
+// and lenient options. Its result is not used, so a failure there only shortens the list.
+fn columns_read<T>(
+    file_path: &str,
+    parse_row: &mut impl FnMut(&mut Row) -> Result<T, Box<dyn Error>>,
+) -> Result<Vec<String>, Box<dyn Error>> {
     let mut reader = Reader::from_path(file_path)?;
-    let mut teams = Vec::new();
+    let mut columns = Columns::from_reader(&mut reader, file_path)?;
+    columns.read = Some(RefCell::default());
+
+    if let Some(record) = reader.records().next().transpose()? {
+        let mut row = Row {
+            record: &record,
+            columns: &columns,
+            report: &mut LoadReport::new(file_path),
+            options: &LoadOptions::default(),
+            invalid_column: None,
+        };
+        let _ = parse_row(&mut row);
+    }
+    Ok(columns.read.map(RefCell::into_inner).unwrap_or_default())
+}
+
+fn parse_rows<T>(
+    file_path: &str,
+    options: &LoadOptions,
+    parse_row: impl FnMut(&mut Row) -> Result<T, Box<dyn Error>>,
+) -> Result<(Vec<T>, LoadReport), Box<dyn Error>> {
+    let mut stream = RowStream::open(file_path, options, parse_row)?;
+    let rows = stream.by_ref().collect::<Result<Vec<T>, _>>()?;
+    Ok((rows, stream.into_report()))
+}
+
+type RowParser<'a, T> = Box<dyn FnMut(&mut Row) -> Result<T, Box<dyn Error>> + 'a>;
+
+// Parses one record at a time, so memory stays bounded however large the file is. The
+// report fills in as the stream is consumed. When a cache is configured the stream reads
+// the cached snapshot of the parsed rows instead, and the report is complete from the start.
+pub struct RowStream<'a, T> {
+    source: RowSource<'a, T>,
+    report: LoadReport,
+}
+
+enum RowSource<'a, T> {
+    File(Box<FileRows<'a, T>>),
+    Snapshot(std::vec::IntoIter<T>),
+}
+
+struct FileRows<'a, T> {
+    records: csv::StringRecordsIntoIter<File>,
+    columns: Columns,
+    filter_columns: FilterColumns,
+    options: LoadOptions,
+    parse_row: RowParser<'a, T>,
+}
+
+impl<'a, T> RowStream<'a, T> {
+    fn open(
+        file_path: &str,
+        options: &LoadOptions,
+        parse_row: impl FnMut(&mut Row) -> Result<T, Box<dyn Error>> + 'a,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut reader = Reader::from_path(file_path)?;
+        let columns = Columns::from_reader(&mut reader, file_path)?;
+        let filter_columns = FilterColumns::new(&columns, &options.filter);
+
+        Ok(RowStream {
+            source: RowSource::File(Box::new(FileRows {
+                records: reader.into_records(),
+                columns,
+                filter_columns,
+                options: options.clone(),
+                parse_row: Box::new(parse_row),
+            })),
+            report: LoadReport::new(file_path),
+        })
+    }
+
+    pub fn report(&self) -> &LoadReport {
+        &self.report
+    }
+
+    pub fn into_report(self) -> LoadReport {
+        self.report
+    }
+}
+
+// Streams `file_path` through the cache when one is configured, so a later run reads the
+// snapshot `load_rows` saved instead of parsing the file again.
+fn stream_rows<T: Serialize + DeserializeOwned>(
+    file_path: &str,
+    options: &LoadOptions,
+    parse_row: impl FnMut(&mut Row) -> Result<T, Box<dyn Error>> + 'static,
+) -> Result<RowStream<'static, T>, Box<dyn Error>> {
+    if options.cache_dir.is_none() {
+        return RowStream::open(file_path, options, parse_row);
+    }
+    let (rows, report) = load_rows(file_path, options, parse_row)?;
+    Ok(RowStream { source: RowSource::Snapshot(rows.into_iter()), report })
+}
+
+impl<T> Iterator for RowStream<'_, T> {
+    type Item = Result<T, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.source {
+            RowSource::File(file) => file.next_row(&mut self.report),
+            RowSource::Snapshot(rows) => rows.next().map(Ok),
+        }
+    }
+}
 
-    for result in reader.records() {
-        let record = result?;
+impl<T> FileRows<'_, T> {
+    fn next_row(&mut self, report: &mut LoadReport) -> Option<Result<T, Box<dyn Error>>> {
+        loop {
+            let record = match self.records.next()? {
+                Ok(record) => record,
+                Err(e) => return Some(Err(e.into())),
+            };
+            report.rows_read += 1;
 
-        let playoffs = match record[4].to_lowercase().as_str() {
-            "true" => true,
-            "false" => false,
-            _ => {
-                eprintln!("Error parsing playoffs '{}': Invalid value", record[4].to_string());
+            if let Some(reason) = self.filter_columns.skip_reason(&record, &self.options.filter) {
+                report.drop_row(reason);
                 continue;
             }
-        };
 
-        let three_point_percentage = if record[12].to_string() == "NA" {
-            0.0
-        } else {
-            match record[12].parse() {
-                Ok(percentage) => percentage,
-                Err(e) => {
-                    eprintln!("Error parsing 3P percentage '{}': {:?}", record[12].to_string(), e);
-                    continue;
+            let mut row = Row {
+                record: &record,
+                columns: &self.columns,
+                report: &mut *report,
+                options: &self.options,
+                invalid_column: None,
+            };
+            let parsed = match (self.parse_row)(&mut row) {
+                Ok(parsed) => parsed,
+                Err(e) => return Some(Err(e)),
+            };
+
+            match row.invalid_column {
+                Some(column) => report.drop_row(&format!("invalid {}", column)),
+                None => {
+                    report.rows_kept += 1;
+                    return Some(Ok(parsed));
                 }
             }
+        }
+    }
+}
+
+fn parse_player(row: &mut Row) -> Result<Player, Box<dyn Error>> {
+    Ok(Player {
+        id: row.required("player_id")?,
+        seas_id: row.required("seas_id")?,
+        name: row.text("player")?,
+        team_abbreviation: row.text("tm")?,
+        season: row.required("season")?,
+        lg: row.text("lg")?,
+        games: row.optional("g")?,
+        minutes: row.optional("mp")?,
+        stint_weight: 1.0,
+        fg_percent: row.optional("fg_percent")?,
+        fg_percent_from_x2p_range: row.optional("fg_percent_from_x2p_range")?,
+        fg_percent_from_x3p_range: row.optional("fg_percent_from_x3p_range")?,
+        attempts: ShotAttempts::default(),
+        shot_profile: ShotProfile {
+            avg_dist_fga: row.optional("avg_dist_fga")?,
+            percent_fga_from_x2p_range: row.optional("percent_fga_from_x2p_range")?,
+            percent_fga_from_x0_3_range: row.optional("percent_fga_from_x0_3_range")?,
+            percent_fga_from_x3_10_range: row.optional("percent_fga_from_x3_10_range")?,
+            percent_fga_from_x10_16_range: row.optional("percent_fga_from_x10_16_range")?,
+            percent_fga_from_x16_3p_range: row.optional("percent_fga_from_x16_3p_range")?,
+            percent_fga_from_x3p_range: row.optional("percent_fga_from_x3p_range")?,
+            fg_percent_from_x0_3_range: row.optional("fg_percent_from_x0_3_range")?,
+            fg_percent_from_x3_10_range: row.optional("fg_percent_from_x3_10_range")?,
+            fg_percent_from_x10_16_range: row.optional("fg_percent_from_x10_16_range")?,
+            fg_percent_from_x16_3p_range: row.optional("fg_percent_from_x16_3p_range")?,
+            percent_assisted_x2p_fg: row.optional("percent_assisted_x2p_fg")?,
+            percent_assisted_x3p_fg: row.optional("percent_assisted_x3p_fg")?,
+            percent_dunks_of_fga: row.optional("percent_dunks_of_fga")?,
+            num_of_dunks: row.optional("num_of_dunks")?,
+            percent_corner_3s_of_3pa: row.optional("percent_corner_3s_of_3pa")?,
+            corner_3_point_percent: row.optional("corner_3_point_percent")?,
+            num_heaves_attempted: row.optional("num_heaves_attempted")?,
+            num_heaves_made: row.optional("num_heaves_made")?,
+        },
+    })
+}
+
+pub fn load_player_data(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<Player>, LoadReport), Box<dyn Error>> {
+    let (players, mut report) = load_rows(file_path, &pre_policy_options(options), parse_player)?;
+    let players = apply_multi_team_policy(players, options, &mut report);
+    Ok((players, report))
+}
+
+// Streaming version of `load_player_data` for when the rows don't need to be held at once.
+// Both cache the rows read before the multi-team policy and the team and minutes filters,
+// so they share one snapshot whatever policy and filters are used.
+pub fn stream_player_data(file_path: &str, options: &LoadOptions) -> Result<PlayerStream, Box<dyn Error>> {
+    Ok(PlayerStream {
+        rows: stream_rows(file_path, &pre_policy_options(options), parse_player)?,
+        policy: options.multi_team_policy,
+        filter: options.filter.clone(),
+        pending: None,
+        ready: VecDeque::new(),
+    })
+}
+
+// The options player rows are read with before the multi-team policy runs.
+fn pre_policy_options(options: &LoadOptions) -> LoadOptions {
+    LoadOptions {
+        filter: options.filter.season_and_league(),
+        ..options.clone()
+    }
+}
+
+// Rows of a traded player's season sit next to each other, the TOT row first and the
+// stints after it in the order they were played.
+fn same_player_season(a: &Player, b: &Player) -> bool {
+    a.id == b.id && a.season == b.season
+}
+
+fn apply_multi_team_policy(players: Vec<Player>, options: &LoadOptions, report: &mut LoadReport) -> Vec<Player> {
+    let mut seasons: Vec<Vec<Player>> = Vec::new();
+    for player in players {
+        match seasons.last_mut() {
+            Some(rows) if same_player_season(&rows[0], &player) => rows.push(player),
+            _ => seasons.push(vec![player]),
+        }
+    }
+
+    seasons
+        .into_iter()
+        .flat_map(|rows| resolve_player_season(rows, options.multi_team_policy, &options.filter, report))
+        .collect()
+}
+
+fn drop_resolved(report: &mut LoadReport, reason: &str) {
+    report.drop_row(reason);
+    report.rows_kept -= 1;
+}
+
+// Applies the minutes filter, the policy and then the team filter to the rows of one
+// player season, moving dropped rows from kept to dropped in the report. The minimum is
+// checked against the whole season, so a traded player is kept or dropped as one and the
+// weights of their stints still sum to 1. The team filter sees the team the policy
+// credited, so TotalOnly keeps the combined row of a player whose main team is listed.
+fn resolve_player_season(
+    rows: Vec<Player>,
+    policy: MultiTeamPolicy,
+    filter: &RowFilter,
+    report: &mut LoadReport,
+) -> Vec<Player> {
+    if let Some(min_minutes) = filter.min_minutes {
+        let season_minutes = match rows.iter().find(|p| p.team_abbreviation == MULTI_TEAM_TOTAL) {
+            Some(total) => total.minutes,
+            None => rows.iter().map(|p| p.minutes).sum(),
         };
+        if season_minutes.is_none_or(|minutes| minutes < min_minutes) {
+            for _ in &rows {
+                drop_resolved(report, "below minimum minutes");
+            }
+            return Vec::new();
+        }
+    }
+
+    let mut resolved = apply_policy(rows, policy, report);
+    resolved.retain(|player| {
+        let keep = filter.keeps_team(&player.team_abbreviation);
+        if !keep {
+            drop_resolved(report, "other team");
+        }
+        keep
+    });
+    resolved
+}
 
-        let fg_percentage = if record[9].to_string() == "NA" {
-            0.0
-        } else {
-            match record[9].parse() {
-                Ok(percentage) => percentage,
-                Err(e) => {
-                    eprintln!("Error parsing FG percentage '{}': {:?}", record[9].to_string(), e);
-                    continue;
+fn apply_policy(rows: Vec<Player>, policy: MultiTeamPolicy, report: &mut LoadReport) -> Vec<Player> {
+    let Some(total_index) = rows.iter().position(|p| p.team_abbreviation == MULTI_TEAM_TOTAL) else {
+        return rows;
+    };
+    let mut stints = rows;
+    let mut total = stints.remove(total_index);
+
+    match policy {
+        MultiTeamPolicy::TotalOnly => {
+            // max_by_key keeps the last maximum, so ties go to the later team.
+            if let Some(main_stint) = stints.iter().max_by_key(|p| p.minutes.unwrap_or(0)) {
+                total.team_abbreviation = main_stint.team_abbreviation.clone();
+            }
+            for _ in &stints {
+                drop_resolved(report, "multi-team stint");
+            }
+            vec![total]
+        }
+        MultiTeamPolicy::StintsOnly | MultiTeamPolicy::MinutesWeighted => {
+            drop_resolved(report, "multi-team total row");
+            if policy == MultiTeamPolicy::MinutesWeighted {
+                let total_minutes = total
+                    .minutes
+                    .or_else(|| stints.iter().map(|p| p.minutes).sum())
+                    .filter(|&m| m > 0);
+                let stint_count = stints.len() as f64;
+                for stint in &mut stints {
+                    stint.stint_weight = match (stint.minutes, total_minutes) {
+                        (Some(minutes), Some(total_minutes)) => minutes as f64 / total_minutes as f64,
+                        _ => 1.0 / stint_count,
+                    };
                 }
             }
-        };
+            stints
+        }
+    }
+}
 
-        let two_point_percentage = if record[15].to_string() == "NA" {
-            0.0
-        } else {
-            match record[15].parse() {
-                Ok(percentage) => percentage,
-                Err(e) => {
-                    eprintln!("Error parsing 2P percentage '{}': {:?}", record[15].to_string(), e);
-                    continue;
-                }
+// Holds back one player season at a time so the multi-team policy can see all of its rows.
+pub struct PlayerStream {
+    rows: RowStream<'static, Player>,
+    policy: MultiTeamPolicy,
+    filter: RowFilter,
+    pending: Option<Result<Player, Box<dyn Error>>>,
+    ready: VecDeque<Player>,
+}
+
+impl PlayerStream {
+    pub fn report(&self) -> &LoadReport {
+        self.rows.report()
+    }
+}
+
+impl Iterator for PlayerStream {
+    type Item = Result<Player, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(player) = self.ready.pop_front() {
+                return Some(Ok(player));
             }
-        };
 
-        let points_per_game = if record[27].to_string() == "NA" {
-            0.0
-        } else {
-            match record[27].parse() {
-                Ok(points) => points,
-                Err(e) => {
-                    eprintln!("Error parsing points per game '{}': {:?}", record[27].to_string(), e);
-                    continue;
+            let first = match self.pending.take().or_else(|| self.rows.next())? {
+                Ok(player) => player,
+                Err(e) => return Some(Err(e)),
+            };
+            let mut season_rows = vec![first];
+            for next in self.rows.by_ref() {
+                match next {
+                    Ok(player) if same_player_season(&season_rows[0], &player) => season_rows.push(player),
+                    other => {
+                        self.pending = Some(other);
+                        break;
+                    }
                 }
             }
+
+            let resolved = resolve_player_season(season_rows, self.policy, &self.filter, &mut self.rows.report);
+            self.ready.extend(resolved);
+        }
+    }
+}
+
+fn parse_team(row: &mut Row) -> Result<Team, Box<dyn Error>> {
+    Ok(Team {
+        abbreviation: row.text("abbreviation")?,
+        name: row.text("team")?,
+        season: row.required("season")?,
+        lg: row.text("lg")?,
+        playoffs: row.flag("playoffs")?,
+        fg_percentage: row.nullable("fg_percent")?,
+        two_point_percentage: row.nullable("x2p_percent")?,
+        three_point_percentage: row.nullable("x3p_percent")?,
+        points_per_game: row.nullable("pts_per_game")?,
+        summary: None,
+        opponent: None,
+        season_stats: None,
+        opponent_stats: None,
+    })
+}
+
+pub fn load_team_data(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<Team>, LoadReport), Box<dyn Error>> {
+    load_rows(file_path, options, parse_team)
+}
+
+pub fn stream_team_data(file_path: &str, options: &LoadOptions) -> Result<RowStream<'static, Team>, Box<dyn Error>> {
+    stream_rows(file_path, options, parse_team)
+}
+
+pub fn load_player_per100(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<PlayerPer100>, LoadReport), Box<dyn Error>> {
+    load_rows(file_path, options, |row| {
+        Ok(PlayerPer100 {
+            seas_id: row.required("seas_id")?,
+            season: row.required("season")?,
+            player_id: row.required("player_id")?,
+            name: row.text("player")?,
+            position: row.text("pos")?,
+            age: row.optional("age")?,
+            experience: row.optional("experience")?,
+            lg: row.text("lg")?,
+            team_abbreviation: row.text("tm")?,
+            games: row.required("g")?,
+            games_started: row.optional("gs")?,
+            minutes_played: row.required("mp")?,
+            fg_per_100_poss: row.optional("fg_per_100_poss")?,
+            fga_per_100_poss: row.optional("fga_per_100_poss")?,
+            fg_percent: row.optional("fg_percent")?,
+            x3p_per_100_poss: row.optional("x3p_per_100_poss")?,
+            x3pa_per_100_poss: row.optional("x3pa_per_100_poss")?,
+            x3p_percent: row.optional("x3p_percent")?,
+            x2p_per_100_poss: row.optional("x2p_per_100_poss")?,
+            x2pa_per_100_poss: row.optional("x2pa_per_100_poss")?,
+            x2p_percent: row.optional("x2p_percent")?,
+            ft_per_100_poss: row.optional("ft_per_100_poss")?,
+            fta_per_100_poss: row.optional("fta_per_100_poss")?,
+            ft_percent: row.optional("ft_percent")?,
+            orb_per_100_poss: row.optional("orb_per_100_poss")?,
+            drb_per_100_poss: row.optional("drb_per_100_poss")?,
+            trb_per_100_poss: row.optional("trb_per_100_poss")?,
+            ast_per_100_poss: row.optional("ast_per_100_poss")?,
+            stl_per_100_poss: row.optional("stl_per_100_poss")?,
+            blk_per_100_poss: row.optional("blk_per_100_poss")?,
+            tov_per_100_poss: row.optional("tov_per_100_poss")?,
+            pf_per_100_poss: row.optional("pf_per_100_poss")?,
+            pts_per_100_poss: row.optional("pts_per_100_poss")?,
+            o_rtg: row.optional("o_rtg")?,
+            d_rtg: row.optional("d_rtg")?,
+        })
+    })
+}
+
+fn parse_play_by_play(row: &mut Row) -> Result<PlayByPlay, Box<dyn Error>> {
+    Ok(PlayByPlay {
+        seas_id: row.required("seas_id")?,
+        season: row.required("season")?,
+        player_id: row.required("player_id")?,
+        name: row.text("player")?,
+        position: row.text("pos")?,
+        age: row.optional("age")?,
+        experience: row.optional("experience")?,
+        lg: row.text("lg")?,
+        team_abbreviation: row.text("tm")?,
+        games: row.required("g")?,
+        minutes_played: row.required("mp")?,
+        pg_percent: row.optional("pg_percent")?,
+        sg_percent: row.optional("sg_percent")?,
+        sf_percent: row.optional("sf_percent")?,
+        pf_percent: row.optional("pf_percent")?,
+        c_percent: row.optional("c_percent")?,
+        on_court_plus_minus_per_100_poss: row.optional("on_court_plus_minus_per_100_poss")?,
+        net_plus_minus_per_100_poss: row.optional("net_plus_minus_per_100_poss")?,
+        bad_pass_turnover: row.required("bad_pass_turnover")?,
+        lost_ball_turnover: row.required("lost_ball_turnover")?,
+        shooting_foul_committed: row.required("shooting_foul_committed")?,
+        offensive_foul_committed: row.required("offensive_foul_committed")?,
+        shooting_foul_drawn: row.required("shooting_foul_drawn")?,
+        offensive_foul_drawn: row.optional("offensive_foul_drawn")?,
+        points_generated_by_assists: row.required("points_generated_by_assists")?,
+        and1: row.required("and1")?,
+        fga_blocked: row.required("fga_blocked")?,
+    })
+}
+
+pub fn load_play_by_play(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<PlayByPlay>, LoadReport), Box<dyn Error>> {
+    load_rows(file_path, options, parse_play_by_play)
+}
+
+pub fn stream_play_by_play(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<RowStream<'static, PlayByPlay>, Box<dyn Error>> {
+    stream_rows(file_path, options, parse_play_by_play)
+}
+
+pub fn load_player_career_info(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<PlayerCareer>, LoadReport), Box<dyn Error>> {
+    load_rows(file_path, options, |row| {
+        Ok(PlayerCareer {
+            player_id: row.required("player_id")?,
+            name: row.text("player")?,
+            birth_year: row.optional("birth_year")?,
+            hall_of_fame: row.flag("hof")?,
+            num_seasons: row.required("num_seasons")?,
+            first_season: row.required("first_seas")?,
+            last_season: row.required("last_seas")?,
+        })
+    })
+}
+
+pub fn load_player_season_info(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<PlayerSeasonInfo>, LoadReport), Box<dyn Error>> {
+    load_rows(file_path, options, |row| {
+        Ok(PlayerSeasonInfo {
+            season: row.required("season")?,
+            seas_id: row.required("seas_id")?,
+            player_id: row.required("player_id")?,
+            name: row.text("player")?,
+            birth_year: row.optional("birth_year")?,
+            position: row.text("pos")?,
+            age: row.optional("age")?,
+            lg: row.text("lg")?,
+            team_abbreviation: row.text("tm")?,
+            experience: row.required("experience")?,
+        })
+    })
+}
+
+pub fn load_award_shares(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<AwardShare>, LoadReport), Box<dyn Error>> {
+    load_rows(file_path, options, |row| {
+        Ok(AwardShare {
+            season: row.required("season")?,
+            award: row.text("award")?,
+            name: row.text("player")?,
+            age: row.optional("age")?,
+            team_abbreviation: row.text("tm")?,
+            first_place_votes: row.optional("first")?,
+            pts_won: row.optional("pts_won")?,
+            pts_max: row.optional("pts_max")?,
+            share: row.optional("share")?,
+            winner: row.optional_flag("winner")?,
+            seas_id: row.required("seas_id")?,
+            player_id: row.required("player_id")?,
+        })
+    })
+}
+
+pub fn load_all_star_selections(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<AllStarSelection>, LoadReport), Box<dyn Error>> {
+    load_rows(file_path, options, |row| {
+        Ok(AllStarSelection {
+            name: row.text("player")?,
+            team: row.text("team")?,
+            lg: row.text("lg")?,
+            season: row.required("season")?,
+            replaced: row.flag("replaced")?,
+        })
+    })
+}
+
+pub fn load_team_abbreviations(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<TeamAbbreviation>, LoadReport), Box<dyn Error>> {
+    load_rows(file_path, options, |row| {
+        Ok(TeamAbbreviation {
+            season: row.required("season")?,
+            lg: row.text("lg")?,
+            name: row.text("team")?,
+            playoffs: row.flag("playoffs")?,
+            abbreviation: row.text("abbreviation")?,
+        })
+    })
+}
+
+pub fn load_end_of_season_teams(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<EndOfSeasonTeam>, LoadReport), Box<dyn Error>> {
+    load_rows(file_path, options, |row| {
+        Ok(EndOfSeasonTeam {
+            season: row.required("season")?,
+            lg: row.text("lg")?,
+            team_type: row.text("type")?,
+            number_tm: row.text("number_tm")?,
+            name: row.text("player")?,
+            position: row.text("position")?,
+            seas_id: row.required("seas_id")?,
+            player_id: row.required("player_id")?,
+            team_abbreviation: row.text("tm")?,
+            age: row.optional("age")?,
+        })
+    })
+}
+
+pub fn load_end_of_season_team_votes(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<EndOfSeasonTeamVote>, LoadReport), Box<dyn Error>> {
+    load_rows(file_path, options, |row| {
+        Ok(EndOfSeasonTeamVote {
+            season: row.required("season")?,
+            lg: row.text("lg")?,
+            team_type: row.text("type")?,
+            number_tm: row.text("number_tm")?,
+            position: row.text("position")?,
+            name: row.text("player")?,
+            age: row.optional("age")?,
+            team_abbreviation: row.text("tm")?,
+            pts_won: row.optional("pts_won")?,
+            pts_max: row.optional("pts_max")?,
+            share: row.optional("share")?,
+            first_team_votes: row.optional("x1st_tm")?,
+            second_team_votes: row.optional("x2nd_tm")?,
+            third_team_votes: row.optional("x3rd_tm")?,
+            seas_id: row.required("seas_id")?,
+            player_id: row.required("player_id")?,
+        })
+    })
+}
+
+pub fn load_team_summaries(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<TeamSummary>, LoadReport), Box<dyn Error>> {
+    load_rows(file_path, options, |row| {
+        Ok(TeamSummary {
+            season: row.required("season")?,
+            lg: row.text("lg")?,
+            name: row.text("team")?,
+            abbreviation: row.text("abbreviation")?,
+            playoffs: row.flag("playoffs")?,
+            age: row.optional("age")?,
+            wins: row.optional("w")?,
+            losses: row.optional("l")?,
+            pythagorean_wins: row.optional("pw")?,
+            pythagorean_losses: row.optional("pl")?,
+            mov: row.optional("mov")?,
+            sos: row.optional("sos")?,
+            srs: row.optional("srs")?,
+            o_rtg: row.optional("o_rtg")?,
+            d_rtg: row.optional("d_rtg")?,
+            n_rtg: row.optional("n_rtg")?,
+            pace: row.optional("pace")?,
+            f_tr: row.optional("f_tr")?,
+            x3p_ar: row.optional("x3p_ar")?,
+            ts_percent: row.optional("ts_percent")?,
+            e_fg_percent: row.optional("e_fg_percent")?,
+            tov_percent: row.optional("tov_percent")?,
+            orb_percent: row.optional("orb_percent")?,
+            ft_fga: row.optional("ft_fga")?,
+            opp_e_fg_percent: row.optional("opp_e_fg_percent")?,
+            opp_tov_percent: row.optional("opp_tov_percent")?,
+            opp_drb_percent: row.optional("opp_drb_percent")?,
+            opp_ft_fga: row.optional("opp_ft_fga")?,
+            arena: row.optional("arena")?,
+            attendance: row.optional("attend")?,
+            attendance_per_game: row.optional("attend_g")?,
+        })
+    })
+}
+
+// League average rows have no abbreviation ("NA") and never match a team. The league is
+// part of the key so ABA and NBA teams of the same season never meet.
+fn team_key(season: u32, lg: &str, abbreviation: &str) -> Option<(u32, String, String)> {
+    (abbreviation != "NA").then(|| (season, lg.to_string(), abbreviation.to_string()))
+}
+
+// Attaches each summary to the team with the same season and abbreviation. Returns the
+// number of teams that received one.
+pub fn merge_team_summaries(teams: &mut [Team], summaries: Vec<TeamSummary>) -> usize {
+    let mut summaries_by_team: HashMap<(u32, String, String), TeamSummary> = summaries
+        .into_iter()
+        .filter_map(|s| Some((team_key(s.season, &s.lg, &s.abbreviation)?, s)))
+        .collect();
+
+    let mut merged = 0;
+    for team in teams.iter_mut() {
+        let Some(key) = team_key(team.season, &team.lg, &team.abbreviation) else {
+            continue;
         };
+        if let Some(summary) = summaries_by_team.remove(&key) {
+            team.summary = Some(summary);
+            merged += 1;
+        }
+    }
+    merged
+}
 
-        let team = Team {
-            abbreviation: record[3].to_string(),
-            name: record[2].to_string(),
-            season: record[0].parse()?,
-            playoffs,
-            fg_percentage,
-            two_point_percentage,
-            three_point_percentage,
-            points_per_game,
+impl StatBasis {
+    fn column_suffix(&self) -> &'static str {
+        match self {
+            StatBasis::PerGame => "_per_game",
+            StatBasis::Per100Poss => "_per_100_poss",
+            StatBasis::Totals => "",
+        }
+    }
+}
+
+// Team and opponent files share one layout; opponent columns carry an "opp_" prefix and
+// counting stats a basis suffix (fg_per_game, opp_fg_per_100_poss, fg, ...).
+fn load_box_scores(
+    file_path: &str,
+    basis: StatBasis,
+    prefix: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<TeamBoxScore>, LoadReport), Box<dyn Error>> {
+    let suffix = basis.column_suffix();
+    let counting = |stat: &str| format!("{}{}{}", prefix, stat, suffix);
+    let percent = |stat: &str| format!("{}{}_percent", prefix, stat);
+    let minutes_column = match basis {
+        StatBasis::PerGame => "mp_per_game",
+        StatBasis::Per100Poss | StatBasis::Totals => "mp",
+    };
+
+    load_rows(file_path, options, |row| {
+        Ok(TeamBoxScore {
+            season: row.required("season")?,
+            lg: row.text("lg")?,
+            name: row.text("team")?,
+            abbreviation: row.text("abbreviation")?,
+            playoffs: row.flag("playoffs")?,
+            games: row.optional("g")?,
+            basis,
+            minutes: row.optional(minutes_column)?,
+            fg: row.optional(&counting("fg"))?,
+            fga: row.optional(&counting("fga"))?,
+            fg_percent: row.optional(&percent("fg"))?,
+            x3p: row.optional(&counting("x3p"))?,
+            x3pa: row.optional(&counting("x3pa"))?,
+            x3p_percent: row.optional(&percent("x3p"))?,
+            x2p: row.optional(&counting("x2p"))?,
+            x2pa: row.optional(&counting("x2pa"))?,
+            x2p_percent: row.optional(&percent("x2p"))?,
+            ft: row.optional(&counting("ft"))?,
+            fta: row.optional(&counting("fta"))?,
+            ft_percent: row.optional(&percent("ft"))?,
+            orb: row.optional(&counting("orb"))?,
+            drb: row.optional(&counting("drb"))?,
+            trb: row.optional(&counting("trb"))?,
+            ast: row.optional(&counting("ast"))?,
+            stl: row.optional(&counting("stl"))?,
+            blk: row.optional(&counting("blk"))?,
+            tov: row.optional(&counting("tov"))?,
+            pf: row.optional(&counting("pf"))?,
+            pts: row.optional(&counting("pts"))?,
+        })
+    })
+}
+
+pub fn load_team_box_scores(
+    file_path: &str,
+    basis: StatBasis,
+    options: &LoadOptions,
+) -> Result<(Vec<TeamBoxScore>, LoadReport), Box<dyn Error>> {
+    load_box_scores(file_path, basis, "", options)
+}
+
+pub fn load_opponent_stats(
+    file_path: &str,
+    basis: StatBasis,
+    options: &LoadOptions,
+) -> Result<(Vec<TeamBoxScore>, LoadReport), Box<dyn Error>> {
+    load_box_scores(file_path, basis, "opp_", options)
+}
+
+// Attaches the opponent shooting block to each team from a per-game opponent file.
+// Returns the number of teams that received one.
+pub fn merge_opponent_stats(teams: &mut [Team], opponent_stats: &[TeamBoxScore]) -> usize {
+    let opponents_by_team: HashMap<(u32, String, String), &TeamBoxScore> = opponent_stats
+        .iter()
+        .filter_map(|o| Some((team_key(o.season, &o.lg, &o.abbreviation)?, o)))
+        .collect();
+
+    let mut merged = 0;
+    for team in teams.iter_mut() {
+        let Some(opponent) = team_key(team.season, &team.lg, &team.abbreviation).and_then(|key| opponents_by_team.get(&key))
+        else {
+            continue;
+        };
+        team.opponent = Some(OpponentShooting {
+            fg_percent: opponent.fg_percent,
+            x2p_percent: opponent.x2p_percent,
+            x3p_percent: opponent.x3p_percent,
+            x3pa_per_game: opponent.x3pa.filter(|_| opponent.basis == StatBasis::PerGame),
+            pts_per_game: opponent.pts.filter(|_| opponent.basis == StatBasis::PerGame),
+        });
+        merged += 1;
+    }
+    merged
+}
+
+pub fn load_player_totals(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(Vec<PlayerTotals>, LoadReport), Box<dyn Error>> {
+    load_rows(file_path, options, |row| {
+        Ok(PlayerTotals {
+            seas_id: row.required("seas_id")?,
+            season: row.required("season")?,
+            player_id: row.required("player_id")?,
+            name: row.text("player")?,
+            lg: row.text("lg")?,
+            team_abbreviation: row.text("tm")?,
+            games: row.required("g")?,
+            minutes_played: row.optional("mp")?,
+            fg: row.optional("fg")?,
+            fga: row.optional("fga")?,
+            x3p: row.optional("x3p")?,
+            x3pa: row.optional("x3pa")?,
+            x2p: row.optional("x2p")?,
+            x2pa: row.optional("x2pa")?,
+        })
+    })
+}
+
+// Season attempt counts keyed by seas_id, for when Player Totals.csv is missing. A player
+// on the floor for `mp` minutes sees about pace * mp / 48 of the team's possessions, which
+// turns the per 100 possession rates into counts. Multi-team total rows have no pace of
+// their own and get the sum of the player's stints instead.
+pub fn estimate_shot_attempts(per_100: &[PlayerPer100], summaries: &[TeamSummary]) -> HashMap<u32, ShotAttempts> {
+    let pace_by_team: HashMap<(u32, String, String), f64> = summaries
+        .iter()
+        .filter_map(|s| Some((team_key(s.season, &s.lg, &s.abbreviation)?, s.pace?)))
+        .collect();
+
+    let mut attempts = HashMap::new();
+    let mut stints: HashMap<(u32, u32), Vec<ShotAttempts>> = HashMap::new();
+    for row in per_100.iter().filter(|r| r.team_abbreviation != MULTI_TEAM_TOTAL) {
+        let Some(pace) =
+            team_key(row.season, &row.lg, &row.team_abbreviation).and_then(|key| pace_by_team.get(&key))
+        else {
+            continue;
+        };
+        let possessions = pace * row.minutes_played as f64 / 48.0;
+        let count = |rate: Option<f64>| rate.map(|r| r * possessions / 100.0);
+        let estimate = ShotAttempts {
+            fga: count(row.fga_per_100_poss),
+            x2pa: count(row.x2pa_per_100_poss),
+            x3pa: count(row.x3pa_per_100_poss),
+        };
+
+        stints.entry((row.player_id, row.season)).or_default().push(estimate.clone());
+        attempts.insert(row.seas_id, estimate);
+    }
+
+    for row in per_100.iter().filter(|r| r.team_abbreviation == MULTI_TEAM_TOTAL) {
+        let Some(stints) = stints.get(&(row.player_id, row.season)) else {
+            continue;
+        };
+        attempts.insert(
+            row.seas_id,
+            ShotAttempts {
+                fga: stints.iter().map(|s| s.fga).sum(),
+                x2pa: stints.iter().map(|s| s.x2pa).sum(),
+                x3pa: stints.iter().map(|s| s.x3pa).sum(),
+            },
+        );
+    }
+    attempts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_load_player_data_row_count() {
+        let (players, report) =
+            load_player_data("NBA Stats (1947-Present)/Player Shooting.csv", &LoadOptions::default()).unwrap();
+
+        assert_eq!(players.len(), 16785 - 1629);
+        assert_eq!(report.rows_read, 16785);
+        assert_eq!(report.rows_kept, 16785 - 1629);
+        assert_eq!(report.rows_dropped["multi-team total row"], 1629);
+        assert!(players.iter().all(|p| p.team_abbreviation != "TOT"));
+        assert_eq!(players[0].id, 5025);
+        assert_eq!(players[0].seas_id, 31136);
+        assert_eq!(players[0].name, "A.J. Green");
+        assert_eq!(players[0].season, 2024);
+    }
+
+    fn load_players_with_policy(policy: MultiTeamPolicy) -> (Vec<Player>, LoadReport) {
+        let options = LoadOptions {
+            multi_team_policy: policy,
+            ..Default::default()
         };
+        load_player_data("NBA Stats (1947-Present)/Player Shooting.csv", &options).unwrap()
+    }
+
+    #[test]
+    fn test_multi_team_policy_total_only() {
+        let (players, report) = load_players_with_policy(MultiTeamPolicy::TotalOnly);
+
+        let seasons: HashSet<(u32, u32)> = players.iter().map(|p| (p.id, p.season)).collect();
+        assert_eq!(seasons.len(), players.len());
+        assert_eq!(report.rows_kept + report.rows_dropped["multi-team stint"], 16785);
+        assert!(players.iter().all(|p| p.team_abbreviation != "TOT"));
 
-        teams.push(team);
+        // Alec Burks played 43 games for DET and 23 for NYK in 2024.
+        let burks = players.iter().find(|p| p.name == "Alec Burks" && p.season == 2024).unwrap();
+        assert_eq!(burks.team_abbreviation, "DET");
+        assert_eq!(burks.games, Some(66));
+        assert_eq!(burks.minutes, Some(1212));
     }
 
-    Ok(teams)
+    #[test]
+    fn test_multi_team_policy_minutes_weighted() {
+        let (players, _) = load_players_with_policy(MultiTeamPolicy::MinutesWeighted);
+
+        let mut weights: HashMap<(u32, u32), f64> = HashMap::new();
+        for player in &players {
+            *weights.entry((player.id, player.season)).or_default() += player.stint_weight;
+        }
+        assert!(weights.values().all(|w| (w - 1.0).abs() < 1e-9));
+
+        let burks: Vec<&Player> = players.iter().filter(|p| p.name == "Alec Burks" && p.season == 2024).collect();
+        assert_eq!(burks.len(), 2);
+        assert!((burks[0].stint_weight - 901.0 / 1212.0).abs() < 1e-12);
+        assert!((burks[1].stint_weight - 311.0 / 1212.0).abs() < 1e-12);
+        assert_eq!(MultiTeamPolicy::parse("Weighted"), Some(MultiTeamPolicy::MinutesWeighted));
+        assert_eq!(MultiTeamPolicy::parse("tot"), None);
+    }
+
+    #[test]
+    fn test_load_team_abbreviations() {
+        let (rows, report) =
+            load_team_abbreviations("NBA Stats (1947-Present)/Team Abbrev.csv", &LoadOptions::default()).unwrap();
+
+        assert_eq!(rows.len(), 1841);
+        assert_eq!(report.total_dropped(), 0);
+        assert_eq!(rows[0].abbreviation, "ATL");
+        assert_eq!(rows[0].name, "Atlanta Hawks");
+        assert!(!rows[0].playoffs);
+    }
+
+    #[test]
+    fn test_load_with_league_filter() {
+        let options = LoadOptions {
+            filter: RowFilter {
+                leagues: LeagueFilter::only(&["ABA"]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (teams, report) = load_team_data("NBA Stats (1947-Present)/Team Stats Per Game.csv", &options).unwrap();
+
+        assert!(!teams.is_empty());
+        assert!(teams.iter().all(|t| t.lg == "ABA"));
+        assert_eq!(report.rows_kept + report.rows_dropped["other league"], 1845);
+
+        // Award shares have no league column, so the filter leaves them alone.
+        let (awards, award_report) =
+            load_award_shares("NBA Stats (1947-Present)/Player Award Shares.csv", &options).unwrap();
+        assert_eq!(awards.len(), 3293);
+        assert_eq!(award_report.total_dropped(), 0);
+    }
+
+    #[test]
+    fn test_row_filter_pushdown() {
+        // Under TotalOnly every kept row is a whole season, so each one meets the minimum.
+        let options = LoadOptions {
+            multi_team_policy: MultiTeamPolicy::TotalOnly,
+            filter: RowFilter {
+                seasons: Some(2020..=2022),
+                teams: vec!["BOS".to_string(), "DET".to_string()],
+                min_minutes: Some(500),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (players, report) = load_player_data("NBA Stats (1947-Present)/Player Shooting.csv", &options).unwrap();
+
+        assert!(!players.is_empty());
+        assert!(players.iter().all(|p| (2020..=2022).contains(&p.season)));
+        assert!(players.iter().all(|p| p.team_abbreviation == "BOS" || p.team_abbreviation == "DET"));
+        assert!(players.iter().all(|p| p.minutes.unwrap() >= 500));
+        assert_eq!(report.rows_read, 16785);
+        assert_eq!(report.rows_kept, players.len());
+        assert_eq!(report.rows_kept + report.total_dropped(), 16785);
+        assert!(report.rows_dropped["outside season range"] > 0);
+        assert!(report.rows_dropped["other team"] > 0);
+        assert!(report.rows_dropped["below minimum minutes"] > 0);
+    }
+
+    #[test]
+    fn test_filters_run_after_multi_team_policy() {
+        let burks = |policy: MultiTeamPolicy, filter: RowFilter| {
+            let options = LoadOptions {
+                multi_team_policy: policy,
+                filter: RowFilter { seasons: Some(2024..=2024), ..filter },
+                ..Default::default()
+            };
+            let (players, report) =
+                load_player_data("NBA Stats (1947-Present)/Player Shooting.csv", &options).unwrap();
+            assert!(players.iter().all(|p| p.team_abbreviation != "TOT"));
+            assert_eq!(report.rows_kept, players.len());
+            let rows: Vec<(String, Option<u32>, f64)> = players
+                .into_iter()
+                .filter(|p| p.name == "Alec Burks")
+                .map(|p| (p.team_abbreviation, p.minutes, p.stint_weight))
+                .collect();
+            rows
+        };
+        let knicks = || RowFilter { teams: vec!["NYK".to_string()], ..Default::default() };
+        let starters = || RowFilter { min_minutes: Some(1000), ..Default::default() };
+
+        // The combined row is credited to DET, so a NYK-only run leaves it out.
+        assert!(burks(MultiTeamPolicy::TotalOnly, knicks()).is_empty());
+        assert_eq!(burks(MultiTeamPolicy::StintsOnly, knicks()), vec![("NYK".to_string(), Some(311), 1.0)]);
+        assert_eq!(
+            burks(MultiTeamPolicy::MinutesWeighted, knicks()),
+            vec![("NYK".to_string(), Some(311), 311.0 / 1212.0)]
+        );
+
+        // 1212 minutes over the season clears the minimum though neither stint does.
+        assert_eq!(burks(MultiTeamPolicy::TotalOnly, starters()), vec![("DET".to_string(), Some(1212), 1.0)]);
+        assert_eq!(burks(MultiTeamPolicy::StintsOnly, starters()).len(), 2);
+        let weighted = burks(MultiTeamPolicy::MinutesWeighted, starters());
+        assert_eq!(weighted.len(), 2);
+        assert!((weighted.iter().map(|(_, _, w)| w).sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(burks(MultiTeamPolicy::StintsOnly, RowFilter { min_minutes: Some(1300), ..Default::default() }).is_empty());
+    }
+
+    #[test]
+    fn test_stream_matches_load() {
+        for policy in [MultiTeamPolicy::TotalOnly, MultiTeamPolicy::StintsOnly, MultiTeamPolicy::MinutesWeighted] {
+            let options = LoadOptions {
+                multi_team_policy: policy,
+                filter: RowFilter {
+                    seasons: Some(2023..=2024),
+                    teams: vec!["DET".to_string(), "NYK".to_string(), "PHO".to_string()],
+                    min_minutes: Some(300),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let (loaded, load_report) =
+                load_player_data("NBA Stats (1947-Present)/Player Shooting.csv", &options).unwrap();
+            let mut stream = stream_player_data("NBA Stats (1947-Present)/Player Shooting.csv", &options).unwrap();
+            let streamed: Vec<Player> = stream.by_ref().map(Result::unwrap).collect();
+
+            assert_eq!(streamed.len(), loaded.len());
+            assert!(streamed
+                .iter()
+                .zip(&loaded)
+                .all(|(s, l)| s.seas_id == l.seas_id && s.stint_weight == l.stint_weight));
+            assert_eq!(stream.report().rows_kept, load_report.rows_kept);
+            assert_eq!(stream.report().rows_dropped, load_report.rows_dropped);
+        }
+
+        let mut teams = stream_team_data("NBA Stats (1947-Present)/Team Stats Per Game.csv", &LoadOptions::default())
+            .unwrap();
+        let first = teams.next().unwrap().unwrap();
+        assert_eq!((first.season, first.abbreviation.as_str()), (2024, "ATL"));
+        assert_eq!(teams.report().rows_read, 1);
+    }
+
+    #[test]
+    fn test_stream_player_data_reads_cache() {
+        let cache_dir = "Player Stream Cache Test Dir";
+        let options = |policy| LoadOptions {
+            multi_team_policy: policy,
+            filter: RowFilter { seasons: Some(2024..=2024), min_minutes: Some(300), ..Default::default() },
+            cache_dir: Some(cache_dir.to_string()),
+            ..Default::default()
+        };
+        let stream = |options: &LoadOptions| {
+            let mut stream = stream_player_data("NBA Stats (1947-Present)/Player Shooting.csv", options).unwrap();
+            let players: Vec<u32> = stream.by_ref().map(|p| p.unwrap().seas_id).collect();
+            (players, stream.report().clone())
+        };
+
+        let (first, first_report) = stream(&options(MultiTeamPolicy::StintsOnly));
+        let (second, second_report) = stream(&options(MultiTeamPolicy::StintsOnly));
+        // The snapshot is taken before the policy runs, so another policy reads it too.
+        let (total_only, total_only_report) = stream(&options(MultiTeamPolicy::TotalOnly));
+        let (uncached, _) = stream(&LoadOptions { cache_dir: None, ..options(MultiTeamPolicy::TotalOnly) });
+        std::fs::remove_dir_all(cache_dir).unwrap();
+
+        assert!(!first_report.from_cache);
+        assert!(second_report.from_cache);
+        assert!(total_only_report.from_cache);
+        assert_eq!(second, first);
+        assert_eq!(second_report.rows_kept, first_report.rows_kept);
+        assert_eq!(second_report.rows_dropped, first_report.rows_dropped);
+        assert_eq!(total_only, uncached);
+    }
+
+    #[test]
+    fn test_load_team_data_row_count() {
+        let (teams, report) =
+            load_team_data("NBA Stats (1947-Present)/Team Stats Per Game.csv", &LoadOptions::default()).unwrap();
+
+        assert_eq!(teams.len(), 1845);
+        assert_eq!(report.rows_read, 1845);
+        assert_eq!(report.total_dropped(), 0);
+        assert_eq!(teams[0].abbreviation, "ATL");
+        assert_eq!(teams[0].season, 2024);
+    }
+
+    #[test]
+    fn test_load_player_shot_profile() {
+        let (players, report) =
+            load_player_data("NBA Stats (1947-Present)/Player Shooting.csv", &LoadOptions::default()).unwrap();
+
+        assert!(report.parse_failures.is_empty());
+
+        let profile = &players[0].shot_profile;
+        assert_eq!(profile.avg_dist_fga, Some(24.1));
+        assert_eq!(profile.percent_fga_from_x0_3_range, Some(0.026));
+        assert_eq!(profile.percent_fga_from_x3p_range, Some(0.862));
+        assert_eq!(profile.fg_percent_from_x10_16_range, Some(0.333));
+        assert_eq!(profile.percent_assisted_x3p_fg, Some(0.942));
+        assert_eq!(profile.num_of_dunks, Some(0));
+        assert_eq!(profile.percent_corner_3s_of_3pa, Some(0.225));
+        assert_eq!(profile.corner_3_point_percent, Some(0.447));
+        assert_eq!(profile.num_heaves_attempted, Some(0));
+    }
+
+    #[test]
+    fn test_load_player_per100() {
+        let (rows, report) =
+            load_player_per100("NBA Stats (1947-Present)/Per 100 Poss.csv", &LoadOptions::default()).unwrap();
+
+        assert_eq!(rows.len(), 26223);
+        assert_eq!(report.total_dropped(), 0);
+        assert!(report.parse_failures.is_empty());
+
+        let green = &rows[0];
+        assert_eq!(green.seas_id, 31136);
+        assert_eq!(green.player_id, 5025);
+        assert_eq!(green.team_abbreviation, "MIL");
+        assert_eq!(green.minutes_played, 614);
+        assert_eq!(green.pts_per_100_poss, Some(19.7));
+        assert_eq!(green.o_rtg, Some(122.0));
+        assert_eq!(green.d_rtg, Some(120.0));
+    }
+
+    #[test]
+    fn test_load_play_by_play_joins_shooting() {
+        let options = LoadOptions::default();
+        let (rows, report) =
+            load_play_by_play("NBA Stats (1947-Present)/Player Play By Play.csv", &options).unwrap();
+
+        assert_eq!(rows.len(), 16785);
+        assert_eq!(report.total_dropped(), 0);
+
+        let green = &rows[0];
+        assert_eq!((green.seas_id, green.player_id), (31136, 5025));
+        assert_eq!(green.sg_percent, Some(65.0));
+        assert_eq!(green.pf_percent, None);
+        assert_eq!(green.on_court_plus_minus_per_100_poss, Some(4.8));
+        assert_eq!(green.points_generated_by_assists, 72);
+
+        // Between them the two policies keep every shooting row.
+        let mut shooting_ids: HashSet<u32> = HashSet::new();
+        for policy in [MultiTeamPolicy::StintsOnly, MultiTeamPolicy::TotalOnly] {
+            let (players, _) = load_players_with_policy(policy);
+            shooting_ids.extend(players.iter().map(|p| p.seas_id));
+        }
+        assert!(rows.iter().all(|r| shooting_ids.contains(&r.seas_id)));
+    }
+
+    #[test]
+    fn test_load_player_career_and_season_info() {
+        let options = LoadOptions::default();
+        let (careers, career_report) =
+            load_player_career_info("NBA Stats (1947-Present)/Player Career Info.csv", &options).unwrap();
+        let (seasons, season_report) =
+            load_player_season_info("NBA Stats (1947-Present)/Player Season Info.csv", &options).unwrap();
+
+        assert_eq!(careers.len(), 5209);
+        assert_eq!(career_report.total_dropped(), 0);
+        assert_eq!(careers.iter().filter(|c| c.hall_of_fame).count(), 168);
+
+        assert_eq!(seasons.len(), 31870);
+        assert_eq!(season_report.total_dropped(), 0);
+        assert_eq!(seasons.iter().filter(|s| s.age.is_none()).count(), 22);
+        assert_eq!(seasons[0].name, "Al Brightman");
+        assert_eq!(seasons[0].lg, "BAA");
+    }
+
+    #[test]
+    fn test_load_honors_files() {
+        let options = LoadOptions::default();
+        let (award_shares, award_report) =
+            load_award_shares("NBA Stats (1947-Present)/Player Award Shares.csv", &options).unwrap();
+        let (all_stars, all_star_report) =
+            load_all_star_selections("NBA Stats (1947-Present)/All-Star Selections.csv", &options).unwrap();
+        let (teams, team_report) =
+            load_end_of_season_teams("NBA Stats (1947-Present)/End of Season Teams.csv", &options).unwrap();
+        let (votes, vote_report) =
+            load_end_of_season_team_votes("NBA Stats (1947-Present)/End of Season Teams (Voting).csv", &options)
+                .unwrap();
+
+        assert_eq!(award_shares.len(), 3293);
+        assert_eq!(all_stars.len(), 2000);
+        assert_eq!(teams.len(), 2120);
+        assert_eq!(votes.len(), 1964);
+        for report in [&award_report, &all_star_report, &team_report, &vote_report] {
+            assert_eq!(report.total_dropped(), 0);
+            assert!(report.parse_failures.is_empty());
+        }
+
+        assert_eq!(award_shares[0].award, "dpoy");
+        assert_eq!(award_shares[0].winner, Some(true));
+        assert_eq!(award_shares.iter().filter(|a| a.winner.is_none()).count(), 1);
+        assert_eq!(all_stars.iter().filter(|a| a.replaced).count(), 87);
+        assert_eq!(votes[0].first_team_votes, Some(100));
+    }
+
+    #[test]
+    fn test_merge_team_summaries() {
+        let options = LoadOptions::default();
+        let (mut teams, _) =
+            load_team_data("NBA Stats (1947-Present)/Team Stats Per Game.csv", &options).unwrap();
+        let (summaries, report) =
+            load_team_summaries("NBA Stats (1947-Present)/Team Summaries.csv", &options).unwrap();
+
+        assert_eq!(summaries.len(), 1845);
+        assert_eq!(report.total_dropped(), 0);
+        assert!(report.parse_failures.is_empty());
+
+        let merged = merge_team_summaries(&mut teams, summaries);
+        let league_averages = teams.iter().filter(|t| t.abbreviation == "NA").count();
+        assert_eq!(merged, teams.len() - league_averages);
+
+        let celtics = teams.iter().find(|t| t.season == 2024 && t.abbreviation == "BOS").unwrap();
+        let summary = celtics.summary.as_ref().unwrap();
+        assert_eq!((summary.wins, summary.losses), (Some(64), Some(18)));
+        assert_eq!(summary.n_rtg, Some(11.6));
+        assert_eq!(summary.arena.as_deref(), Some("TD Garden"));
+        assert!((summary.win_percentage().unwrap() - 64.0 / 82.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_load_opponent_stats() {
+        let options = LoadOptions::default();
+        let (per_game, per_game_report) =
+            load_opponent_stats("NBA Stats (1947-Present)/Opponent Stats Per Game.csv", StatBasis::PerGame, &options)
+                .unwrap();
+        let (per_100, per_100_report) = load_opponent_stats(
+            "NBA Stats (1947-Present)/Opponent Stats Per 100 Poss.csv",
+            StatBasis::Per100Poss,
+            &options,
+        )
+        .unwrap();
+        let (totals, totals_report) =
+            load_opponent_stats("NBA Stats (1947-Present)/Opponent Totals.csv", StatBasis::Totals, &options).unwrap();
+
+        assert_eq!(per_game.len(), 1845);
+        assert_eq!(per_100.len(), 1402);
+        assert_eq!(totals.len(), 1845);
+        for report in [&per_game_report, &per_100_report, &totals_report] {
+            assert_eq!(report.total_dropped(), 0);
+            assert!(report.parse_failures.is_empty());
+        }
+
+        assert_eq!(per_game[0].abbreviation, "ATL");
+        assert_eq!(per_game[0].minutes, Some(242.1));
+        assert_eq!(per_game[0].fg_percent, Some(0.495));
+        assert_eq!(per_game[0].pts, Some(120.5));
+        assert_eq!(per_100[0].pts, Some(119.4));
+        assert_eq!(totals[0].pts, Some(9882.0));
+        assert_eq!(totals[0].minutes, Some(19855.0));
+    }
+
+    #[test]
+    fn test_load_team_box_scores() {
+        let options = LoadOptions::default();
+        let (per_100, per_100_report) = load_team_box_scores(
+            "NBA Stats (1947-Present)/Team Stats Per 100 Poss.csv",
+            StatBasis::Per100Poss,
+            &options,
+        )
+        .unwrap();
+        let (totals, totals_report) =
+            load_team_box_scores("NBA Stats (1947-Present)/Team Totals.csv", StatBasis::Totals, &options).unwrap();
+
+        assert_eq!(per_100.len(), 1402);
+        assert_eq!(totals.len(), 1845);
+        assert_eq!(per_100_report.total_dropped(), 0);
+        assert_eq!(totals_report.total_dropped(), 0);
+
+        assert_eq!(per_100[0].abbreviation, "ATL");
+        assert_eq!(per_100[0].pts, Some(117.2));
+        assert_eq!(per_100[0].minutes, Some(19855.0));
+        assert_eq!(totals[0].pts, Some(9703.0));
+        assert_eq!(totals[0].games, Some(82));
+    }
+
+    #[test]
+    fn test_merge_opponent_stats() {
+        let options = LoadOptions::default();
+        let (mut teams, _) =
+            load_team_data("NBA Stats (1947-Present)/Team Stats Per Game.csv", &options).unwrap();
+        let (opponents, _) =
+            load_opponent_stats("NBA Stats (1947-Present)/Opponent Stats Per Game.csv", StatBasis::PerGame, &options)
+                .unwrap();
+
+        let merged = merge_opponent_stats(&mut teams, &opponents);
+        assert_eq!(merged, 1845 - 87);
+
+        let hawks = &teams[0];
+        let opponent = hawks.opponent.as_ref().unwrap();
+        assert_eq!(opponent.fg_percent, Some(0.495));
+        assert_eq!(opponent.x3p_percent, Some(0.384));
+        assert_eq!(opponent.x2p_percent, Some(0.57));
+        assert!((hawks.fg_percentage_edge().unwrap() - (0.465 - 0.495)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_estimate_shot_attempts() {
+        let options = LoadOptions::default();
+        let (per_100, _) = load_player_per100("NBA Stats (1947-Present)/Per 100 Poss.csv", &options).unwrap();
+        let (summaries, _) =
+            load_team_summaries("NBA Stats (1947-Present)/Team Summaries.csv", &options).unwrap();
+        let attempts = estimate_shot_attempts(&per_100, &summaries);
+
+        let burks: Vec<&PlayerPer100> =
+            per_100.iter().filter(|p| p.name == "Alec Burks" && p.season == 2024).collect();
+        let estimate = |tm: &str| &attempts[&burks.iter().find(|p| p.team_abbreviation == tm).unwrap().seas_id];
+        let (total, det, nyk) = (estimate("TOT"), estimate("DET"), estimate("NYK"));
+
+        // 901 minutes in Detroit against 311 in New York.
+        assert!(det.fga.unwrap() > 2.0 * nyk.fga.unwrap());
+        assert!((total.fga.unwrap() - det.fga.unwrap() - nyk.fga.unwrap()).abs() < 1e-9);
+        assert!((total.x3pa.unwrap() - det.x3pa.unwrap() - nyk.x3pa.unwrap()).abs() < 1e-9);
+        assert!((det.x2pa.unwrap() + det.x3pa.unwrap() - det.fga.unwrap()).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_load_player_totals() {
+        let file_path = "Player Totals Test.csv";
+        std::fs::write(
+            file_path,
+            "seas_id,season,player_id,player,birth_year,pos,age,experience,lg,tm,g,gs,mp,fg,fga,fg_percent,x3p,x3pa,\
+             x3p_percent,x2p,x2pa,x2p_percent\n\
+             31000,2024,5000,Player A,NA,SG,24,2,NBA,MIL,56,0,614,91,216,0.421,76,186,0.409,15,30,0.5\n\
+             1000,1950,200,Player B,NA,C,28,3,NBA,SYR,64,NA,NA,300,850,0.353,NA,NA,NA,300,850,0.353\n",
+        )
+        .unwrap();
+        let (totals, report) = load_player_totals(file_path, &LoadOptions::default()).unwrap();
+        std::fs::remove_file(file_path).unwrap();
+
+        assert_eq!(report.rows_kept, 2);
+        assert_eq!(totals[0].attempts(), ShotAttempts { fga: Some(216.0), x2pa: Some(30.0), x3pa: Some(186.0) });
+        assert_eq!(totals[0].minutes_played, Some(614));
+        // Minutes and threes were not recorded in the early seasons.
+        assert_eq!(totals[1].minutes_played, None);
+        assert_eq!(totals[1].attempts().x3pa, None);
+    }
+
+    #[test]
+    fn test_load_team_data_missing_stats_are_none() {
+        let (teams, _) =
+            load_team_data("NBA Stats (1947-Present)/Team Stats Per Game.csv", &LoadOptions::default()).unwrap();
+
+        let celtics_1950 = teams
+            .iter()
+            .find(|t| t.season == 1950 && t.abbreviation == "BOS")
+            .unwrap();
+        assert_eq!(celtics_1950.three_point_percentage, None);
+        assert!(celtics_1950.fg_percentage.is_some());
+    }
+
+    #[test]
+    fn test_load_team_data_reordered_columns() {
+        let file_path = "Reordered Team Test.csv";
+        std::fs::write(
+            file_path,
+            "pts_per_game,abbreviation,x2p_percent,team,lg,season,x3p_percent,playoffs,fg_percent\n\
+             110.5,TEA,0.55,Team A,NBA,2022,0.35,TRUE,0.45\n",
+        )
+        .unwrap();
+
+        let (teams, _) = load_team_data(file_path, &LoadOptions::default()).unwrap();
+        std::fs::remove_file(file_path).unwrap();
+
+        assert_eq!(teams.len(), 1);
+        assert_eq!(teams[0].abbreviation, "TEA");
+        assert_eq!(teams[0].name, "Team A");
+        assert_eq!(teams[0].season, 2022);
+        assert_eq!(teams[0].lg, "NBA");
+        assert!(teams[0].playoffs);
+        assert_eq!(teams[0].fg_percentage, Some(0.45));
+        assert_eq!(teams[0].two_point_percentage, Some(0.55));
+        assert_eq!(teams[0].three_point_percentage, Some(0.35));
+        assert_eq!(teams[0].points_per_game, Some(110.5));
+    }
+
+    #[test]
+    fn test_load_team_data_missing_column() {
+        let file_path = "Missing Column Team Test.csv";
+        std::fs::write(
+            file_path,
+            "season,lg,team,abbreviation,playoffs,fg_percent,x3p_percent,x2p_percent\n\
+             2022,NBA,Team A,TEA,TRUE,0.45,0.35,0.55\n",
+        )
+        .unwrap();
+
+        let error = load_team_data(file_path, &LoadOptions::default()).err().unwrap();
+        std::fs::remove_file(file_path).unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "Missing column 'pts_per_game' in 'Missing Column Team Test.csv'"
+        );
+    }
+
+    #[test]
+    fn test_load_report_counts_failures() {
+        let file_path = "Load Report Test.csv";
+        std::fs::write(
+            file_path,
+            "season,lg,team,abbreviation,playoffs,fg_percent,x2p_percent,x3p_percent,pts_per_game\n\
+             2022,NBA,Team A,TEA,TRUE,0.45,0.55,0.35,110.5\n\
+             2022,NBA,Team B,TEB,maybe,0.45,0.55,0.35,110.5\n\
+             2022,NBA,Team C,TEC,FALSE,bad,0.55,0.35,110.5\n\
+             2022,NBA,Team D,TED,FALSE,0.45,0.55,NA,110.5\n",
+        )
+        .unwrap();
+
+        let (teams, report) = load_team_data(file_path, &LoadOptions::default()).unwrap();
+        let strict_error = load_team_data(file_path, &LoadOptions { strict: true, ..Default::default() }).err().unwrap();
+        std::fs::remove_file(file_path).unwrap();
+
+        assert_eq!(teams.len(), 2);
+        assert_eq!(report.rows_read, 4);
+        assert_eq!(report.rows_kept, 2);
+        assert_eq!(report.rows_dropped["invalid playoffs"], 1);
+        assert_eq!(report.rows_dropped["invalid fg_percent"], 1);
+        assert_eq!(report.parse_failures["fg_percent"].count, 1);
+        assert_eq!(report.parse_failures["fg_percent"].samples, vec!["bad".to_string()]);
+        assert!(!report.parse_failures.contains_key("x3p_percent"));
+
+        assert_eq!(
+            strict_error.to_string(),
+            "Invalid value 'maybe' for column 'playoffs' on line 3 of 'Load Report Test.csv'"
+        );
+    }
 }
\ No newline at end of file
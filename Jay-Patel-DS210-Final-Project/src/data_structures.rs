@@ -1,28 +1,697 @@
-#[derive(Clone, Debug)]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Player {
     pub id: u32,
+    pub seas_id: u32,
     pub name: String,
     pub team_abbreviation: String,
     pub season: u32,
-    pub fg_percent: f64,
-    pub fg_percent_from_x2p_range: f64,
-    pub fg_percent_from_x3p_range: f64,
+    pub lg: String,
+    pub games: Option<u32>,
+    pub minutes: Option<u32>,
+    // Share of the player's season this row stands for; below 1.0 only for team stints
+    // under MultiTeamPolicy::MinutesWeighted.
+    pub stint_weight: f64,
+    pub fg_percent: Option<f64>,
+    pub fg_percent_from_x2p_range: Option<f64>,
+    pub fg_percent_from_x3p_range: Option<f64>,
+    pub attempts: ShotAttempts,
+    pub shot_profile: ShotProfile,
 }
 
-#[derive(Clone, Debug)]
+// Season attempt counts. Player Shooting.csv has none, so these come from Player
+// Totals.csv, or are estimated from the player's per 100 possession rates and the team's
+// pace when that file is missing.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShotAttempts {
+    pub fga: Option<f64>,
+    pub x2pa: Option<f64>,
+    pub x3pa: Option<f64>,
+}
+
+// Shot diet from Player Shooting.csv. Zone shares are fractions of all FGA; the 2P/3P
+// accuracies live on Player itself.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ShotProfile {
+    pub avg_dist_fga: Option<f64>,
+    pub percent_fga_from_x2p_range: Option<f64>,
+    pub percent_fga_from_x0_3_range: Option<f64>,
+    pub percent_fga_from_x3_10_range: Option<f64>,
+    pub percent_fga_from_x10_16_range: Option<f64>,
+    pub percent_fga_from_x16_3p_range: Option<f64>,
+    pub percent_fga_from_x3p_range: Option<f64>,
+    pub fg_percent_from_x0_3_range: Option<f64>,
+    pub fg_percent_from_x3_10_range: Option<f64>,
+    pub fg_percent_from_x10_16_range: Option<f64>,
+    pub fg_percent_from_x16_3p_range: Option<f64>,
+    pub percent_assisted_x2p_fg: Option<f64>,
+    pub percent_assisted_x3p_fg: Option<f64>,
+    pub percent_dunks_of_fga: Option<f64>,
+    pub num_of_dunks: Option<u32>,
+    pub percent_corner_3s_of_3pa: Option<f64>,
+    pub corner_3_point_percent: Option<f64>,
+    pub num_heaves_attempted: Option<u32>,
+    pub num_heaves_made: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Team {
     pub abbreviation: String,
     pub name: String,
     pub season: u32,
+    pub lg: String,
     pub playoffs: bool,
-    pub fg_percentage: f64,
-    pub two_point_percentage: f64,
-    pub three_point_percentage: f64,
-    pub points_per_game: f64,
+    pub fg_percentage: Option<f64>,
+    pub two_point_percentage: Option<f64>,
+    pub three_point_percentage: Option<f64>,
+    pub points_per_game: Option<f64>,
+    pub summary: Option<TeamSummary>,
+    pub opponent: Option<OpponentShooting>,
+    // The team's box scores in every basis, from the TeamSeasonTable.
+    pub season_stats: Option<TeamSeason>,
+    // The opponents' box scores in every basis, from the opponents' TeamSeasonTable.
+    pub opponent_stats: Option<TeamSeason>,
+}
+
+impl Team {
+    // Points in `basis`. Per game points fall back to the team file's own column when no
+    // team season was merged.
+    pub fn points(&self, basis: StatBasis) -> Option<f64> {
+        match self.season_stats.as_ref().and_then(|s| s.stats(basis)) {
+            Some(box_score) => box_score.pts,
+            None if basis == StatBasis::PerGame => self.points_per_game,
+            None => None,
+        }
+    }
+
+    // Opponents' field goal percentage in `basis`, falling back to the per game opponent
+    // block when no opponent season was merged.
+    pub fn opponent_fg_percent(&self, basis: StatBasis) -> Option<f64> {
+        match self.opponent_stats.as_ref().and_then(|s| s.stats(basis)) {
+            Some(box_score) => box_score.fg_percent,
+            None if basis == StatBasis::PerGame => self.opponent.as_ref()?.fg_percent,
+            None => None,
+        }
+    }
+
+    // Own shooting minus what opponents shot against the team; positive means the team
+    // shot better than it allowed.
+    pub fn fg_percentage_edge(&self) -> Option<f64> {
+        Some(self.fg_percentage? - self.opponent.as_ref()?.fg_percent?)
+    }
+
+    pub fn two_point_percentage_edge(&self) -> Option<f64> {
+        Some(self.two_point_percentage? - self.opponent.as_ref()?.x2p_percent?)
+    }
+
+    pub fn three_point_percentage_edge(&self) -> Option<f64> {
+        Some(self.three_point_percentage? - self.opponent.as_ref()?.x3p_percent?)
+    }
+}
+
+// What opponents shot against a team, from the per game opponent file. The other bases
+// are read through `Team::opponent_stats`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpponentShooting {
+    pub fg_percent: Option<f64>,
+    pub x2p_percent: Option<f64>,
+    pub x3p_percent: Option<f64>,
+    pub x3pa_per_game: Option<f64>,
+    pub pts_per_game: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StatBasis {
+    #[default]
+    PerGame,
+    Per100Poss,
+    Totals,
+}
+
+impl StatBasis {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "per-game" => Some(StatBasis::PerGame),
+            "per-100" => Some(StatBasis::Per100Poss),
+            "totals" => Some(StatBasis::Totals),
+            _ => None,
+        }
+    }
+}
+
+// One row of a team or opponent box score file (Team Stats Per Game.csv, Opponent
+// Totals.csv, ...). Counting stats are in the units given by `basis`; `minutes` is per
+// game for PerGame and season totals otherwise.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TeamBoxScore {
+    pub season: u32,
+    pub lg: String,
+    pub name: String,
+    pub abbreviation: String,
+    pub playoffs: bool,
+    pub games: Option<u32>,
+    pub basis: StatBasis,
+    pub minutes: Option<f64>,
+    pub fg: Option<f64>,
+    pub fga: Option<f64>,
+    pub fg_percent: Option<f64>,
+    pub x3p: Option<f64>,
+    pub x3pa: Option<f64>,
+    pub x3p_percent: Option<f64>,
+    pub x2p: Option<f64>,
+    pub x2pa: Option<f64>,
+    pub x2p_percent: Option<f64>,
+    pub ft: Option<f64>,
+    pub fta: Option<f64>,
+    pub ft_percent: Option<f64>,
+    pub orb: Option<f64>,
+    pub drb: Option<f64>,
+    pub trb: Option<f64>,
+    pub ast: Option<f64>,
+    pub stl: Option<f64>,
+    pub blk: Option<f64>,
+    pub tov: Option<f64>,
+    pub pf: Option<f64>,
+    pub pts: Option<f64>,
+}
+
+impl TeamBoxScore {
+    // Stats whose value depends on the basis, in file column order.
+    pub fn counting_stats(&self) -> [(&'static str, Option<f64>); 18] {
+        [
+            ("mp", self.minutes),
+            ("fg", self.fg),
+            ("fga", self.fga),
+            ("x3p", self.x3p),
+            ("x3pa", self.x3pa),
+            ("x2p", self.x2p),
+            ("x2pa", self.x2pa),
+            ("ft", self.ft),
+            ("fta", self.fta),
+            ("orb", self.orb),
+            ("drb", self.drb),
+            ("trb", self.trb),
+            ("ast", self.ast),
+            ("stl", self.stl),
+            ("blk", self.blk),
+            ("tov", self.tov),
+            ("pf", self.pf),
+            ("pts", self.pts),
+        ]
+    }
+
+    pub fn percentages(&self) -> [(&'static str, Option<f64>); 4] {
+        [
+            ("fg_percent", self.fg_percent),
+            ("x3p_percent", self.x3p_percent),
+            ("x2p_percent", self.x2p_percent),
+            ("ft_percent", self.ft_percent),
+        ]
+    }
+}
+
+// One team season with its box score in every normalization that was loaded. Per 100
+// possession stats only exist from 1974 on.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TeamSeason {
+    pub season: u32,
+    pub lg: String,
+    pub name: String,
+    pub abbreviation: String,
+    pub playoffs: bool,
+    pub games: Option<u32>,
+    pub per_game: Option<TeamBoxScore>,
+    pub per_100_poss: Option<TeamBoxScore>,
+    pub totals: Option<TeamBoxScore>,
+}
+
+impl TeamSeason {
+    pub fn stats(&self, basis: StatBasis) -> Option<&TeamBoxScore> {
+        match basis {
+            StatBasis::PerGame => self.per_game.as_ref(),
+            StatBasis::Per100Poss => self.per_100_poss.as_ref(),
+            StatBasis::Totals => self.totals.as_ref(),
+        }
+    }
+}
+
+// Advanced team metrics from Team Summaries.csv. Ratings are per 100 possessions.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TeamSummary {
+    pub season: u32,
+    pub lg: String,
+    pub name: String,
+    pub abbreviation: String,
+    pub playoffs: bool,
+    pub age: Option<f64>,
+    pub wins: Option<u32>,
+    pub losses: Option<u32>,
+    pub pythagorean_wins: Option<u32>,
+    pub pythagorean_losses: Option<u32>,
+    pub mov: Option<f64>,
+    pub sos: Option<f64>,
+    pub srs: Option<f64>,
+    pub o_rtg: Option<f64>,
+    pub d_rtg: Option<f64>,
+    pub n_rtg: Option<f64>,
+    pub pace: Option<f64>,
+    pub f_tr: Option<f64>,
+    pub x3p_ar: Option<f64>,
+    pub ts_percent: Option<f64>,
+    pub e_fg_percent: Option<f64>,
+    pub tov_percent: Option<f64>,
+    pub orb_percent: Option<f64>,
+    pub ft_fga: Option<f64>,
+    pub opp_e_fg_percent: Option<f64>,
+    pub opp_tov_percent: Option<f64>,
+    pub opp_drb_percent: Option<f64>,
+    pub opp_ft_fga: Option<f64>,
+    pub arena: Option<String>,
+    pub attendance: Option<u32>,
+    pub attendance_per_game: Option<u32>,
+}
+
+impl TeamSummary {
+    pub fn win_percentage(&self) -> Option<f64> {
+        let (wins, losses) = (self.wins?, self.losses?);
+        if wins + losses == 0 {
+            return None;
+        }
+        Some(wins as f64 / (wins + losses) as f64)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayerPer100 {
+    pub seas_id: u32,
+    pub season: u32,
+    pub player_id: u32,
+    pub name: String,
+    pub position: String,
+    pub age: Option<u32>,
+    pub experience: Option<u32>,
+    pub lg: String,
+    pub team_abbreviation: String,
+    pub games: u32,
+    pub games_started: Option<u32>,
+    pub minutes_played: u32,
+    pub fg_per_100_poss: Option<f64>,
+    pub fga_per_100_poss: Option<f64>,
+    pub fg_percent: Option<f64>,
+    pub x3p_per_100_poss: Option<f64>,
+    pub x3pa_per_100_poss: Option<f64>,
+    pub x3p_percent: Option<f64>,
+    pub x2p_per_100_poss: Option<f64>,
+    pub x2pa_per_100_poss: Option<f64>,
+    pub x2p_percent: Option<f64>,
+    pub ft_per_100_poss: Option<f64>,
+    pub fta_per_100_poss: Option<f64>,
+    pub ft_percent: Option<f64>,
+    pub orb_per_100_poss: Option<f64>,
+    pub drb_per_100_poss: Option<f64>,
+    pub trb_per_100_poss: Option<f64>,
+    pub ast_per_100_poss: Option<f64>,
+    pub stl_per_100_poss: Option<f64>,
+    pub blk_per_100_poss: Option<f64>,
+    pub tov_per_100_poss: Option<f64>,
+    pub pf_per_100_poss: Option<f64>,
+    pub pts_per_100_poss: Option<f64>,
+    pub o_rtg: Option<f64>,
+    pub d_rtg: Option<f64>,
+}
+
+// The shooting columns of one row of Player Totals.csv, as season counts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayerTotals {
+    pub seas_id: u32,
+    pub season: u32,
+    pub player_id: u32,
+    pub name: String,
+    pub lg: String,
+    pub team_abbreviation: String,
+    pub games: u32,
+    pub minutes_played: Option<u32>,
+    pub fg: Option<f64>,
+    pub fga: Option<f64>,
+    pub x3p: Option<f64>,
+    pub x3pa: Option<f64>,
+    pub x2p: Option<f64>,
+    pub x2pa: Option<f64>,
+}
+
+impl PlayerTotals {
+    pub fn attempts(&self) -> ShotAttempts {
+        ShotAttempts { fga: self.fga, x2pa: self.x2pa, x3pa: self.x3pa }
+    }
+}
+
+// Positional splits are percentages of minutes played (0-100); "NA" means no minutes there.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayByPlay {
+    pub seas_id: u32,
+    pub season: u32,
+    pub player_id: u32,
+    pub name: String,
+    pub position: String,
+    pub age: Option<u32>,
+    pub experience: Option<u32>,
+    pub lg: String,
+    pub team_abbreviation: String,
+    pub games: u32,
+    pub minutes_played: u32,
+    pub pg_percent: Option<f64>,
+    pub sg_percent: Option<f64>,
+    pub sf_percent: Option<f64>,
+    pub pf_percent: Option<f64>,
+    pub c_percent: Option<f64>,
+    pub on_court_plus_minus_per_100_poss: Option<f64>,
+    pub net_plus_minus_per_100_poss: Option<f64>,
+    pub bad_pass_turnover: u32,
+    pub lost_ball_turnover: u32,
+    pub shooting_foul_committed: u32,
+    pub offensive_foul_committed: u32,
+    pub shooting_foul_drawn: u32,
+    pub offensive_foul_drawn: Option<u32>,
+    pub points_generated_by_assists: u32,
+    pub and1: u32,
+    pub fga_blocked: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayerCareer {
+    pub player_id: u32,
+    pub name: String,
+    pub birth_year: Option<u32>,
+    pub hall_of_fame: bool,
+    pub num_seasons: u32,
+    pub first_season: u32,
+    pub last_season: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayerSeasonInfo {
+    pub season: u32,
+    pub seas_id: u32,
+    pub player_id: u32,
+    pub name: String,
+    pub birth_year: Option<u32>,
+    pub position: String,
+    pub age: Option<u32>,
+    pub lg: String,
+    pub team_abbreviation: String,
+    pub experience: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AwardShare {
+    pub season: u32,
+    pub award: String,
+    pub name: String,
+    pub age: Option<u32>,
+    pub team_abbreviation: String,
+    pub first_place_votes: Option<f64>,
+    pub pts_won: Option<f64>,
+    pub pts_max: Option<f64>,
+    pub share: Option<f64>,
+    pub winner: Option<bool>,
+    pub seas_id: u32,
+    pub player_id: u32,
+}
+
+// All-Star Selections.csv has no player_id, so selections are matched to players by name
+// and season. `team` is the conference or captain's team the player was picked for.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AllStarSelection {
+    pub name: String,
+    pub team: String,
+    pub lg: String,
+    pub season: u32,
+    pub replaced: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EndOfSeasonTeam {
+    pub season: u32,
+    pub lg: String,
+    pub team_type: String,
+    pub number_tm: String,
+    pub name: String,
+    pub position: String,
+    pub seas_id: u32,
+    pub player_id: u32,
+    pub team_abbreviation: String,
+    pub age: Option<u32>,
+}
+
+// `number_tm` is "1st".."3rd", "1T".."3T" for ties, or "ORV" for players who received
+// votes without making a team.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EndOfSeasonTeamVote {
+    pub season: u32,
+    pub lg: String,
+    pub team_type: String,
+    pub number_tm: String,
+    pub position: String,
+    pub name: String,
+    pub age: Option<u32>,
+    pub team_abbreviation: String,
+    pub pts_won: Option<f64>,
+    pub pts_max: Option<f64>,
+    pub share: Option<f64>,
+    pub first_team_votes: Option<u32>,
+    pub second_team_votes: Option<u32>,
+    pub third_team_votes: Option<u32>,
+    pub seas_id: u32,
+    pub player_id: u32,
+}
+
+// Recognition a player received in one season. The All-NBA fields also cover the
+// All-ABA and All-BAA teams of those leagues.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Honors {
+    pub mvp_share: Option<f64>,
+    pub dpoy_share: Option<f64>,
+    pub awards_won: Vec<String>,
+    pub all_nba_team: Option<u8>,
+    pub all_nba_vote_share: Option<f64>,
+    pub all_defense_team: Option<u8>,
+    pub all_rookie_team: Option<u8>,
+    pub all_star: bool,
+}
+
+// One team season from Team Abbrev.csv, the reference list of which abbreviation a team
+// used in each season.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TeamAbbreviation {
+    pub season: u32,
+    pub lg: String,
+    pub name: String,
+    pub playoffs: bool,
+    pub abbreviation: String,
+}
+
+// The leagues (NBA, ABA, BAA) a step keeps. An empty filter keeps every league.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LeagueFilter {
+    leagues: Vec<String>,
+}
+
+impl LeagueFilter {
+    pub fn only(leagues: &[&str]) -> Self {
+        LeagueFilter { leagues: leagues.iter().map(|lg| lg.to_string()).collect() }
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.leagues.is_empty()
+    }
+
+    pub fn matches(&self, lg: &str) -> bool {
+        self.is_all() || self.leagues.iter().any(|l| l == lg)
+    }
+}
+
+// Minimums a player season has to meet before an analysis uses it. Games and minutes
+// exclude the whole player season; the attempt and make minimums only hide the percentage
+// they qualify (FG and 2P% on field goals, 3P% on threes), so the player's other
+// percentages are still used.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Qualifier {
+    pub min_games: Option<u32>,
+    pub min_minutes: Option<u32>,
+    pub min_fga: Option<f64>,
+    pub min_x2pa: Option<f64>,
+    pub min_x3pa: Option<f64>,
+    pub min_fg_made: Option<f64>,
+    pub min_x3p_made: Option<f64>,
+}
+
+// Unknown counts never meet a minimum.
+fn meets(value: Option<f64>, minimum: Option<f64>) -> bool {
+    match minimum {
+        Some(minimum) => value.is_some_and(|v| v >= minimum),
+        None => true,
+    }
+}
+
+impl Qualifier {
+    // Basketball-Reference's leaderboard minimums for an 82 game season, prorated to the
+    // length of the season: 58 games (70% of the season), 1,500 minutes, 300 made field
+    // goals, and 82 made threes. Its shooting minimums are on makes, so no attempt minimum
+    // is set.
+    pub fn basketball_reference(season_games: u32) -> Self {
+        let scale = season_games as f64 / 82.0;
+        Qualifier {
+            min_games: Some((58.0 * scale).ceil() as u32),
+            min_minutes: Some((1500.0 * scale).ceil() as u32),
+            min_fg_made: Some((300.0 * scale).ceil()),
+            min_x3p_made: Some((82.0 * scale).ceil()),
+            ..Default::default()
+        }
+    }
+
+    // This qualifier with every minimum that `overrides` sets put in its place.
+    pub fn overridden_by(&self, overrides: &Qualifier) -> Self {
+        Qualifier {
+            min_games: overrides.min_games.or(self.min_games),
+            min_minutes: overrides.min_minutes.or(self.min_minutes),
+            min_fga: overrides.min_fga.or(self.min_fga),
+            min_x2pa: overrides.min_x2pa.or(self.min_x2pa),
+            min_x3pa: overrides.min_x3pa.or(self.min_x3pa),
+            min_fg_made: overrides.min_fg_made.or(self.min_fg_made),
+            min_x3p_made: overrides.min_x3p_made.or(self.min_x3p_made),
+        }
+    }
+
+    pub fn qualifies(&self, player: &Player) -> bool {
+        meets(player.games.map(f64::from), self.min_games.map(f64::from))
+            && meets(player.minutes.map(f64::from), self.min_minutes.map(f64::from))
+    }
+
+    fn field_goals_qualify(&self, player: &Player) -> bool {
+        let made = player.attempts.fga.zip(player.fg_percent).map(|(fga, pct)| fga * pct);
+        self.qualifies(player) && meets(player.attempts.fga, self.min_fga) && meets(made, self.min_fg_made)
+    }
+
+    pub fn fg_percent(&self, player: &Player) -> Option<f64> {
+        player.fg_percent.filter(|_| self.field_goals_qualify(player))
+    }
+
+    pub fn two_point_percent(&self, player: &Player) -> Option<f64> {
+        player
+            .fg_percent_from_x2p_range
+            .filter(|_| self.field_goals_qualify(player) && meets(player.attempts.x2pa, self.min_x2pa))
+    }
+
+    pub fn three_point_percent(&self, player: &Player) -> Option<f64> {
+        let pct = player.fg_percent_from_x3p_range?;
+        let made = player.attempts.x3pa.map(|x3pa| x3pa * pct);
+        let qualified =
+            self.qualifies(player) && meets(player.attempts.x3pa, self.min_x3pa) && meets(made, self.min_x3p_made);
+        Some(pct).filter(|_| qualified)
+    }
+}
+
+// What an analysis runs over: the seasons and leagues it keeps, the minimums a player
+// season has to meet, and the basis team counting stats are read in. `season_qualifiers`
+// replaces `qualifier` for the seasons it lists, so minimums prorated to each season's
+// length can follow a career across seasons. The default keeps everything and reads per
+// game stats.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnalysisScope {
+    pub seasons: RangeInclusive<u32>,
+    pub leagues: LeagueFilter,
+    pub qualifier: Qualifier,
+    pub season_qualifiers: HashMap<u32, Qualifier>,
+    pub team_basis: StatBasis,
+}
+
+impl Default for AnalysisScope {
+    fn default() -> Self {
+        AnalysisScope {
+            seasons: 0..=u32::MAX,
+            leagues: LeagueFilter::default(),
+            qualifier: Qualifier::default(),
+            season_qualifiers: HashMap::new(),
+            team_basis: StatBasis::default(),
+        }
+    }
+}
+
+impl AnalysisScope {
+    pub fn includes(&self, season: u32, lg: &str) -> bool {
+        self.seasons.contains(&season) && self.leagues.matches(lg)
+    }
+
+    pub fn season_label(&self) -> String {
+        season_label(&self.seasons)
+    }
+
+    pub fn qualifier(&self, season: u32) -> &Qualifier {
+        self.season_qualifiers.get(&season).unwrap_or(&self.qualifier)
+    }
+}
+
+// "2022" for a single season, "2019-2022" for a range.
+pub fn season_label(seasons: &RangeInclusive<u32>) -> String {
+    if seasons.start() == seasons.end() {
+        seasons.start().to_string()
+    } else {
+        format!("{}-{}", seasons.start(), seasons.end())
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct MergedData {
     pub player: Player,
     pub team: Team,
-}
\ No newline at end of file
+    pub honors: Honors,
+}
+
+// A 2022 NBA player season on team TEA with every percentage known. Tests override the
+// fields they care about with `..Player::test_fixture()`.
+#[cfg(test)]
+impl Player {
+    pub fn test_fixture() -> Self {
+        Player {
+            id: 1,
+            seas_id: 1,
+            name: "Player 1".to_string(),
+            team_abbreviation: "TEA".to_string(),
+            season: 2022,
+            lg: "NBA".to_string(),
+            games: None,
+            minutes: None,
+            stint_weight: 1.0,
+            fg_percent: Some(0.5),
+            fg_percent_from_x2p_range: Some(0.6),
+            fg_percent_from_x3p_range: Some(0.4),
+            attempts: ShotAttempts::default(),
+            shot_profile: ShotProfile::default(),
+        }
+    }
+}
+
+// The team of `Player::test_fixture`, a 2022 playoff team without summary or opponent rows.
+#[cfg(test)]
+impl Team {
+    pub fn test_fixture() -> Self {
+        Team {
+            abbreviation: "TEA".to_string(),
+            name: "Team A".to_string(),
+            season: 2022,
+            lg: "NBA".to_string(),
+            playoffs: true,
+            fg_percentage: Some(0.45),
+            two_point_percentage: Some(0.55),
+            three_point_percentage: Some(0.35),
+            points_per_game: Some(100.0),
+            summary: None,
+            opponent: None,
+            season_stats: None,
+            opponent_stats: None,
+        }
+    }
+}
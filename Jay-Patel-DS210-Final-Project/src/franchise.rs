@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use crate::data_structures::{AnalysisScope, Player, Team, TeamAbbreviation};
+
+// Team Abbrev.csv has no franchise column, so relocations and renames are listed here,
+// oldest abbreviation first. The last abbreviation of each line is the franchise id;
+// abbreviations not listed are franchises of their own.
+const FRANCHISE_LINEAGES: &[&[&str]] = &[
+    &["TRI", "MLH", "STL", "ATL"],
+    &["NJA", "NYA", "NYN", "NJN", "BRK"],
+    &["CHH", "CHA", "CHO"],
+    &["DNR", "DNA", "DEN"],
+    &["FTW", "DET"],
+    &["PHW", "SFW", "GSW"],
+    &["SDR", "HOU"],
+    &["INA", "IND"],
+    &["BUF", "SDC", "LAC"],
+    &["MNL", "LAL"],
+    &["VAN", "MEM"],
+    &["NOH", "NOK", "NOP"],
+    &["SEA", "OKC"],
+    &["SYR", "PHI"],
+    &["ROC", "CIN", "KCO", "KCK", "SAC"],
+    &["DLC", "TEX", "SAA", "SAS"],
+    &["NOJ", "UTA"],
+    &["CHP", "CHZ", "BAL", "CAP", "WSB", "WAS"],
+    &["ANA", "LAS", "UTS"],
+    &["HSM", "CAR", "SSL"],
+    &["MNM", "MMF", "FLO"],
+    &["NOB", "MMP", "MMT", "MMS"],
+    &["PTP", "MNP", "PTC"],
+    &["OAK", "WSA", "VIR"],
+    &["SDA", "SDS"],
+];
+
+// Abbreviation used by the league average rows.
+const LEAGUE_AVERAGE: &str = "NA";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FranchiseSeason {
+    pub franchise_id: String,
+    pub season: u32,
+    pub lg: String,
+    pub abbreviation: String,
+    pub team_name: String,
+    pub playoffs: bool,
+}
+
+// Team seasons keyed by (season, abbreviation), each tagged with its franchise. The
+// default registry is empty, so every team is its own franchise.
+#[derive(Default)]
+pub struct FranchiseRegistry {
+    seasons: HashMap<(u32, String), FranchiseSeason>,
+}
+
+impl FranchiseRegistry {
+    pub fn new(team_abbreviations: Vec<TeamAbbreviation>) -> Self {
+        let franchise_ids: HashMap<&'static str, &'static str> = FRANCHISE_LINEAGES
+            .iter()
+            .flat_map(|lineage| {
+                let franchise_id = lineage[lineage.len() - 1];
+                lineage.iter().map(move |&abbreviation| (abbreviation, franchise_id))
+            })
+            .collect();
+
+        let seasons = team_abbreviations
+            .into_iter()
+            .filter(|t| t.abbreviation != LEAGUE_AVERAGE)
+            .map(|t| {
+                let franchise_id = franchise_ids.get(t.abbreviation.as_str()).copied().unwrap_or(&t.abbreviation);
+                let franchise_season = FranchiseSeason {
+                    franchise_id: franchise_id.to_string(),
+                    season: t.season,
+                    lg: t.lg,
+                    abbreviation: t.abbreviation.clone(),
+                    team_name: t.name,
+                    playoffs: t.playoffs,
+                };
+                ((t.season, t.abbreviation), franchise_season)
+            })
+            .collect();
+
+        FranchiseRegistry { seasons }
+    }
+
+    pub fn season(&self, season: u32, abbreviation: &str) -> Option<&FranchiseSeason> {
+        self.seasons.get(&(season, abbreviation.to_string()))
+    }
+
+    pub fn franchise_id(&self, season: u32, abbreviation: &str) -> Option<&str> {
+        self.season(season, abbreviation).map(|s| s.franchise_id.as_str())
+    }
+
+    // What joins key a team season on: its franchise id, or the abbreviation itself for
+    // team seasons missing from Team Abbrev.csv.
+    pub fn franchise_key(&self, season: u32, abbreviation: &str) -> String {
+        self.franchise_id(season, abbreviation).unwrap_or(abbreviation).to_string()
+    }
+
+    // Every season of a franchise, oldest first.
+    pub fn history(&self, franchise_id: &str) -> Vec<&FranchiseSeason> {
+        let mut history: Vec<&FranchiseSeason> =
+            self.seasons.values().filter(|s| s.franchise_id == franchise_id).collect();
+        history.sort_by_key(|s| s.season);
+        history
+    }
+
+    // The name the franchise played under in its most recent season.
+    pub fn franchise_name(&self, franchise_id: &str) -> Option<&str> {
+        self.history(franchise_id).last().map(|s| s.team_name.as_str())
+    }
+}
+
+// Players grouped by (season, lg, franchise id), the same key the teams are looked up by.
+pub type PlayersByTeam = HashMap<(u32, String, String), Vec<Player>>;
+
+pub fn team_key(team: &Team, franchises: &FranchiseRegistry) -> (u32, String, String) {
+    (team.season, team.lg.clone(), franchises.franchise_key(team.season, &team.abbreviation))
+}
+
+// Takes the records by value, so a stream of players can be grouped without a copy.
+pub fn filter_data_by_season(
+    player_data: impl IntoIterator<Item = Player>,
+    team_data: impl IntoIterator<Item = Team>,
+    scope: &AnalysisScope,
+    franchises: &FranchiseRegistry,
+) -> (PlayersByTeam, Vec<Team>) {
+    let mut players_by_team: PlayersByTeam = HashMap::new();
+    let filtered_teams: Vec<Team> = team_data
+        .into_iter()
+        .filter(|t| scope.includes(t.season, &t.lg))
+        .collect();
+
+    for player in player_data.into_iter().filter(|p| scope.includes(p.season, &p.lg)) {
+        players_by_team
+            .entry((player.season, player.lg.clone(), franchises.franchise_key(player.season, &player.team_abbreviation)))
+            .or_default()
+            .push(player);
+    }
+
+    (players_by_team, filtered_teams)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_loader::{load_team_abbreviations, LoadOptions};
+    use crate::data_structures::LeagueFilter;
+
+    fn registry() -> FranchiseRegistry {
+        let (team_abbreviations, _) =
+            load_team_abbreviations("NBA Stats (1947-Present)/Team Abbrev.csv", &LoadOptions::default()).unwrap();
+        FranchiseRegistry::new(team_abbreviations)
+    }
+
+    #[test]
+    fn test_relocations_share_a_franchise() {
+        let registry = registry();
+
+        assert_eq!(registry.franchise_id(2012, "NJN"), Some("BRK"));
+        assert_eq!(registry.franchise_id(2013, "BRK"), Some("BRK"));
+        assert_eq!(registry.franchise_id(2008, "SEA"), Some("OKC"));
+        assert_eq!(registry.franchise_id(2002, "CHH"), Some("CHO"));
+        assert_eq!(registry.franchise_id(2014, "CHA"), Some("CHO"));
+        assert_eq!(registry.franchise_id(2010, "NOH"), Some("NOP"));
+        assert_eq!(registry.franchise_id(2022, "BOS"), Some("BOS"));
+        assert_eq!(registry.franchise_name("OKC"), Some("Oklahoma City Thunder"));
+        assert_eq!(registry.season(2008, "SEA").unwrap().team_name, "Seattle SuperSonics");
+    }
+
+    #[test]
+    fn test_unknown_seasons() {
+        let registry = registry();
+
+        assert_eq!(registry.franchise_id(2024, "SEA"), None);
+        assert_eq!(registry.franchise_id(2022, "NA"), None);
+        assert_eq!(registry.franchise_id(2022, "TOT"), None);
+        assert_eq!(registry.franchise_key(2008, "SEA"), "OKC");
+        assert_eq!(registry.franchise_key(2022, "TOT"), "TOT");
+    }
+
+    #[test]
+    fn test_history_has_one_team_per_season() {
+        let registry = registry();
+
+        let kings = registry.history("SAC");
+        assert_eq!(kings.first().unwrap().abbreviation, "ROC");
+        assert_eq!(kings.first().unwrap().season, 1949);
+        let mut seasons: Vec<u32> = kings.iter().map(|s| s.season).collect();
+        seasons.dedup();
+        assert_eq!(seasons.len(), kings.len());
+
+        for lineage in FRANCHISE_LINEAGES {
+            for abbreviation in lineage.iter() {
+                assert!(registry.seasons.values().any(|s| s.abbreviation == *abbreviation));
+            }
+        }
+    }
+
+    #[test]
+    fn test_filter_data_by_season() {
+        let player_data = vec![
+            Player::test_fixture(),
+            Player { id: 2, seas_id: 2, name: "Player 2".to_string(), season: 2021, ..Player::test_fixture() },
+        ];
+
+        let team_data = vec![
+            Team::test_fixture(),
+            Team {
+                abbreviation: "TEB".to_string(),
+                name: "Team B".to_string(),
+                playoffs: false,
+                ..Team::test_fixture()
+            },
+        ];
+
+        let franchises = FranchiseRegistry::default();
+        let scope = AnalysisScope { seasons: 2022..=2022, ..Default::default() };
+        let (players_by_team, filtered_teams) =
+            filter_data_by_season(player_data.clone(), team_data.clone(), &scope, &franchises);
+
+        let key = (2022, "NBA".to_string(), "TEA".to_string());
+        assert_eq!(players_by_team.len(), 1);
+        assert!(players_by_team.contains_key(&key));
+        assert_eq!(players_by_team[&key].len(), 1);
+        assert_eq!(players_by_team[&key][0].id, 1);
+
+        assert_eq!(filtered_teams.len(), 2);
+        assert_eq!(filtered_teams[0].abbreviation, "TEA");
+        assert_eq!(filtered_teams[1].abbreviation, "TEB");
+
+        let (players_by_team, _) = filter_data_by_season(
+            player_data.clone(),
+            team_data.clone(),
+            &AnalysisScope { seasons: 2021..=2022, ..Default::default() },
+            &franchises,
+        );
+        assert_eq!(players_by_team.len(), 2);
+        assert_eq!(players_by_team[&(2021, "NBA".to_string(), "TEA".to_string())][0].id, 2);
+
+        let aba_scope = AnalysisScope { leagues: LeagueFilter::only(&["ABA"]), ..scope };
+        let (players_by_team, filtered_teams) = filter_data_by_season(player_data, team_data, &aba_scope, &franchises);
+        assert!(players_by_team.is_empty());
+        assert!(filtered_teams.is_empty());
+
+        // Players are grouped under the franchise id of their team.
+        let sonics = TeamAbbreviation {
+            season: 2008,
+            lg: "NBA".to_string(),
+            name: "Seattle SuperSonics".to_string(),
+            playoffs: false,
+            abbreviation: "SEA".to_string(),
+        };
+        let franchises = FranchiseRegistry::new(vec![sonics]);
+        let sonic = Player { season: 2008, team_abbreviation: "SEA".to_string(), ..Player::test_fixture() };
+        let (players_by_team, _) = filter_data_by_season(vec![sonic], Vec::new(), &AnalysisScope::default(), &franchises);
+        assert!(players_by_team.contains_key(&(2008, "NBA".to_string(), "OKC".to_string())));
+    }
+}
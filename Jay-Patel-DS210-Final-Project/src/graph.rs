@@ -1,10 +1,13 @@
 use petgraph::{Graph as PetGraph, graph::NodeIndex};
 use std::collections::HashMap;
-use crate::data_structures::MergedData;
+use crate::data_structures::{AnalysisScope, MergedData};
+use crate::franchise::FranchiseRegistry;
 
+#[derive(Default)]
 pub struct Graph {
     pub graph: PetGraph<u32, f64>,
     node_map: HashMap<u32, NodeIndex<u32>>,
+    node_seasons: HashMap<NodeIndex<u32>, u32>,
 }
 
 impl Graph {
@@ -12,6 +15,7 @@ impl Graph {
         Graph {
             graph: PetGraph::new(),
             node_map: HashMap::new(),
+            node_seasons: HashMap::new(),
         }
     }
 
@@ -21,62 +25,90 @@ impl Graph {
         node
     }
 
+    pub fn add_player_node(&mut self, id: u32, season: u32) -> NodeIndex<u32> {
+        let node = self.add_node(id);
+        self.node_seasons.insert(node, season);
+        node
+    }
+
+    pub fn node_season(&self, node: NodeIndex<u32>) -> Option<u32> {
+        self.node_seasons.get(&node).copied()
+    }
+
     pub fn add_edge(&mut self, source: u32, target: u32, weight: f64) {
         let source_node = self.node_map.get(&source).unwrap();
         let target_node = self.node_map.get(&target).unwrap();
         self.graph.add_edge(*source_node, *target_node, weight);
     }
 
-    pub fn construct_from_data(&mut self, merged_data: &[MergedData]) {
-        let mut players_by_team: HashMap<String, Vec<&MergedData>> = HashMap::new();
+    // Builds one node per player season and franchise, so a player traded during a season or
+    // seen in several seasons of `scope` is a separate node each time. Players who miss the
+    // qualifier's games or minutes are left out. Edge weights are distances: the stat
+    // distance between two teammates divided by the smaller of their stint weights, the
+    // most of the season they can have shared, so part-season teammates sit further apart.
+    // Teammates are grouped by franchise id, so a relocated team is the same team.
+    pub fn construct_from_data(&mut self, merged_data: &[MergedData], scope: &AnalysisScope, franchises: &FranchiseRegistry) {
+        let mut players_by_team: HashMap<(u32, String, String), Vec<&MergedData>> = HashMap::new();
     
         for data in merged_data {
-            if data.player.season == 2022 {
+            if scope.includes(data.player.season, &data.team.lg) && scope.qualifier(data.player.season).qualifies(&data.player) {
                 players_by_team
-                    .entry(data.team.abbreviation.clone())
-                    .or_insert_with(Vec::new)
+                    .entry((
+                        data.player.season,
+                        data.team.lg.clone(),
+                        franchises.franchise_key(data.team.season, &data.team.abbreviation),
+                    ))
+                    .or_default()
                     .push(data);
             }
         }
     
-        for (_, team_players) in &players_by_team {
-            let mut player_nodes: HashMap<u32, NodeIndex<u32>> = HashMap::new();
-    
-            for player_data in team_players {
-                let player_id = player_data.player.id;
-                let node_index = self.add_node(player_id);
-                player_nodes.insert(player_id, node_index);
-            }
+        for team_players in players_by_team.values() {
+            let nodes: Vec<NodeIndex<u32>> = team_players
+                .iter()
+                .map(|data| self.add_player_node(data.player.id, data.player.season))
+                .collect();
     
-            for (player1_id, player1_node) in &player_nodes {
-                for (player2_id, player2_node) in &player_nodes {
-                    if player1_id != player2_id {
-                        let player1_data = team_players.iter().find(|data| &data.player.id == player1_id).unwrap();
-                        let player2_data = team_players.iter().find(|data| &data.player.id == player2_id).unwrap();
-                        let weight = Self::calculate_weight(player1_data, player2_data);
-                        self.add_edge(*player1_id, *player2_id, weight);
+            for (i, player1_data) in team_players.iter().enumerate() {
+                for (j, player2_data) in team_players.iter().enumerate() {
+                    let shared = player1_data.player.stint_weight.min(player2_data.player.stint_weight);
+                    if i != j && shared > 0.0 {
+                        if let Some(weight) = Self::calculate_weight(player1_data, player2_data, scope) {
+                            self.graph.add_edge(nodes[i], nodes[j], weight / shared);
+                        }
                     }
                 }
             }
         }
     }
 
-    fn calculate_weight(player1: &MergedData, player2: &MergedData) -> f64 {
-        let player1_fg_percent = player1.player.fg_percent;
-        let player2_fg_percent = player2.player.fg_percent;
-        let fg_percent_diff = (player1_fg_percent - player2_fg_percent).abs();
-
-        let player1_points_per_game = player1.team.points_per_game;
-        let player2_points_per_game = player2.team.points_per_game;
-        let points_per_game_diff = (player1_points_per_game - player2_points_per_game).abs();
-
-        let player1_three_point_percent = player1.player.fg_percent_from_x3p_range;
-        let player2_three_point_percent = player2.player.fg_percent_from_x3p_range;
-        let three_point_percent_diff = (player1_three_point_percent - player2_three_point_percent).abs();
+    // Components missing for either player are dropped and the remaining weights are
+    // rescaled, so an untracked stat neither adds nor removes distance. Percentages a player
+    // does not qualify for count as missing. Returns None when the two players share no
+    // comparable stat. Team points are read in the scope's team basis.
+    fn calculate_weight(player1: &MergedData, player2: &MergedData, scope: &AnalysisScope) -> Option<f64> {
+        let qualifier = scope.qualifier(player1.player.season);
+        let basis = scope.team_basis;
+        let components = [
+            (0.5, qualifier.fg_percent(&player1.player), qualifier.fg_percent(&player2.player)),
+            (0.3, player1.team.points(basis), player2.team.points(basis)),
+            (0.2, qualifier.three_point_percent(&player1.player), qualifier.three_point_percent(&player2.player)),
+        ];
 
-        let weight = 0.5 * fg_percent_diff + 0.3 * points_per_game_diff + 0.2 * three_point_percent_diff;
+        let mut weighted_sum = 0.0;
+        let mut total_coefficient = 0.0;
+        for (coefficient, value1, value2) in components {
+            if let (Some(value1), Some(value2)) = (value1, value2) {
+                weighted_sum += coefficient * (value1 - value2).abs();
+                total_coefficient += coefficient;
+            }
+        }
 
-        weight
+        if total_coefficient > 0.0 {
+            Some(weighted_sum / total_coefficient)
+        } else {
+            None
+        }
     }
 
     pub fn get_node_weight(&self, node_id: u32) -> Option<&u32> {
@@ -95,7 +127,7 @@ impl Graph {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::data_structures::{Player, Team, MergedData};
+    use crate::data_structures::{Honors, LeagueFilter, Player, Team, MergedData};
 
     #[test]
     fn test_add_node() {
@@ -126,48 +158,104 @@ mod tests {
     #[test]
     fn test_construct_from_data() {
         let mut graph = Graph::new();
-        let player1 = Player {
-            id: 1,
-            name: "Player 1".to_string(),
-            team_abbreviation: "TEA".to_string(),
-            season: 2022,
-            fg_percent: 0.5,
-            fg_percent_from_x2p_range: 0.6,
-            fg_percent_from_x3p_range: 0.4,
-        };
         let player2 = Player {
             id: 2,
+            seas_id: 2,
             name: "Player 2".to_string(),
-            team_abbreviation: "TEA".to_string(),
-            season: 2022,
-            fg_percent: 0.6,
-            fg_percent_from_x2p_range: 0.7,
-            fg_percent_from_x3p_range: 0.5,
-        };
-        let team = Team {
-            abbreviation: "TEA".to_string(),
-            name: "Team A".to_string(),
-            season: 2022,
-            playoffs: true,
-            fg_percentage: 0.55,
-            two_point_percentage: 0.65,
-            three_point_percentage: 0.45,
-            points_per_game: 100.0,
+            fg_percent: Some(0.6),
+            fg_percent_from_x2p_range: Some(0.7),
+            fg_percent_from_x3p_range: Some(0.5),
+            ..Player::test_fixture()
         };
+        let team = Team::test_fixture();
         let merged_data = vec![
-            MergedData { player: player1, team: team.clone() },
-            MergedData { player: player2, team },
+            MergedData { player: Player::test_fixture(), team: team.clone(), honors: Honors::default() },
+            MergedData { player: player2, team, honors: Honors::default() },
         ];
-        graph.construct_from_data(&merged_data);
+        graph.construct_from_data(&merged_data, &AnalysisScope::default(), &FranchiseRegistry::default());
         assert_eq!(graph.graph.node_count(), 2);
         assert_eq!(graph.graph.edge_count(), 2);
+        assert!(graph.graph.node_indices().all(|node| graph.node_season(node) == Some(2022)));
+
+        let mut aba_graph = Graph::new();
+        aba_graph.construct_from_data(
+            &merged_data,
+            &AnalysisScope { leagues: LeagueFilter::only(&["ABA"]), ..Default::default() },
+            &FranchiseRegistry::default(),
+        );
+        assert_eq!(aba_graph.graph.node_count(), 0);
+
+        let mut other_season_graph = Graph::new();
+        other_season_graph.construct_from_data(
+            &merged_data,
+            &AnalysisScope { seasons: 2023..=2024, ..Default::default() },
+            &FranchiseRegistry::default(),
+        );
+        assert_eq!(other_season_graph.graph.node_count(), 0);
+
+        // The same two players a season earlier are separate nodes with their own edges.
+        let mut earlier = merged_data.clone();
+        for data in &mut earlier {
+            data.player.season = 2021;
+            data.team.season = 2021;
+        }
+        let mut two_season_graph = Graph::new();
+        two_season_graph.construct_from_data(
+            &[merged_data.clone(), earlier].concat(),
+            &AnalysisScope { seasons: 2021..=2022, ..Default::default() },
+            &FranchiseRegistry::default(),
+        );
+        assert_eq!(two_season_graph.graph.node_count(), 4);
+        assert_eq!(two_season_graph.graph.edge_count(), 4);
+
+        // A stint covering a quarter of player 2's season makes the edges four times longer.
+        let full_weights: Vec<f64> = graph.graph.edge_weights().copied().collect();
+        let mut traded = merged_data.clone();
+        traded[1].player.stint_weight = 0.25;
+        let mut traded_graph = Graph::new();
+        traded_graph.construct_from_data(&traded, &AnalysisScope::default(), &FranchiseRegistry::default());
+        let traded_weights: Vec<f64> = traded_graph.graph.edge_weights().copied().collect();
+        assert!(full_weights.iter().zip(&traded_weights).all(|(full, traded)| (traded - 4.0 * full).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_calculate_weight_with_missing_values() {
+        let player = Player {
+            season: 1975,
+            fg_percent_from_x2p_range: Some(0.5),
+            fg_percent_from_x3p_range: None,
+            ..Player::test_fixture()
+        };
+        let team = Team {
+            season: 1975,
+            playoffs: false,
+            two_point_percentage: Some(0.45),
+            three_point_percentage: None,
+            points_per_game: None,
+            ..Team::test_fixture()
+        };
+        let data1 = MergedData { player: player.clone(), team: team.clone(), honors: Honors::default() };
+        let data2 = MergedData {
+            player: Player { id: 2, fg_percent: Some(0.4), fg_percent_from_x3p_range: Some(0.3), ..player.clone() },
+            team: team.clone(),
+            honors: Honors::default(),
+        };
+        let weight = Graph::calculate_weight(&data1, &data2, &AnalysisScope::default()).unwrap();
+        assert!((weight - 0.1).abs() < 1e-12);
+
+        let data3 = MergedData {
+            player: Player { id: 3, fg_percent: None, ..player },
+            team,
+            honors: Honors::default(),
+        };
+        assert_eq!(Graph::calculate_weight(&data1, &data3, &AnalysisScope::default()), None);
     }
 
     #[test]
     fn test_get_node_weight() {
         let mut graph = Graph::new();
         let node_id = 1;
-        let node_index = graph.add_node(node_id);
+        graph.add_node(node_id);
         assert_eq!(graph.get_node_weight(node_id), Some(&node_id));
     }
 
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use crate::data_structures::{AllStarSelection, AwardShare, EndOfSeasonTeam, EndOfSeasonTeamVote, Honors};
+use crate::registry::PlayerRegistry;
+
+// Per player-season honors keyed by (player_id, season), so they apply to every team
+// stint a player had that season.
+pub struct HonorsIndex {
+    honors: HashMap<(u32, u32), Honors>,
+    pub unresolved_all_stars: Vec<AllStarSelection>,
+}
+
+// "1st", "2nd", "3rd" and the tie markers "1T".."3T" map to the team number; "ORV" has none.
+fn team_number(number_tm: &str) -> Option<u8> {
+    number_tm.chars().next()?.to_digit(10).map(|n| n as u8)
+}
+
+impl HonorsIndex {
+    pub fn build(
+        award_shares: &[AwardShare],
+        all_stars: &[AllStarSelection],
+        end_of_season_teams: &[EndOfSeasonTeam],
+        end_of_season_votes: &[EndOfSeasonTeamVote],
+        registry: &PlayerRegistry,
+    ) -> Self {
+        let mut honors: HashMap<(u32, u32), Honors> = HashMap::new();
+        let mut unresolved_all_stars = Vec::new();
+
+        for award in award_shares {
+            let entry = honors.entry((award.player_id, award.season)).or_default();
+            match award.award.as_str() {
+                "nba mvp" | "aba mvp" => entry.mvp_share = award.share,
+                "dpoy" => entry.dpoy_share = award.share,
+                _ => {}
+            }
+            if award.winner == Some(true) {
+                entry.awards_won.push(award.award.clone());
+            }
+        }
+
+        for selection in all_stars {
+            match registry.player_id_by_name(&selection.name, selection.season) {
+                Some(player_id) => honors.entry((player_id, selection.season)).or_default().all_star = true,
+                None => unresolved_all_stars.push(selection.clone()),
+            }
+        }
+
+        for team in end_of_season_teams {
+            let entry = honors.entry((team.player_id, team.season)).or_default();
+            let number = team_number(&team.number_tm);
+            match team.team_type.as_str() {
+                "All-NBA" | "All-ABA" | "All-BAA" => entry.all_nba_team = number,
+                "All-Defense" => entry.all_defense_team = number,
+                "All-Rookie" => entry.all_rookie_team = number,
+                _ => {}
+            }
+        }
+
+        for vote in end_of_season_votes {
+            if matches!(vote.team_type.as_str(), "All-NBA" | "All-ABA" | "All-BAA") {
+                honors.entry((vote.player_id, vote.season)).or_default().all_nba_vote_share = vote.share;
+            }
+        }
+
+        HonorsIndex {
+            honors,
+            unresolved_all_stars,
+        }
+    }
+
+    pub fn get(&self, player_id: u32, season: u32) -> Honors {
+        self.honors.get(&(player_id, season)).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_structures::PlayerSeasonInfo;
+
+    fn registry() -> PlayerRegistry {
+        let seasons = vec![PlayerSeasonInfo {
+            season: 2022,
+            seas_id: 29918,
+            player_id: 4164,
+            name: "Giannis Antetokounmpo".to_string(),
+            birth_year: None,
+            position: "PF".to_string(),
+            age: Some(27),
+            lg: "NBA".to_string(),
+            team_abbreviation: "MIL".to_string(),
+            experience: 9,
+        }];
+        PlayerRegistry::new(Vec::new(), seasons, Vec::new())
+    }
+
+    #[test]
+    fn test_build_honors() {
+        let award_shares = vec![
+            AwardShare {
+                season: 2022,
+                award: "nba mvp".to_string(),
+                name: "Giannis Antetokounmpo".to_string(),
+                age: Some(27),
+                team_abbreviation: "MIL".to_string(),
+                first_place_votes: Some(1.0),
+                pts_won: Some(595.0),
+                pts_max: Some(1000.0),
+                share: Some(0.595),
+                winner: Some(false),
+                seas_id: 29918,
+                player_id: 4164,
+            },
+            AwardShare {
+                season: 2022,
+                award: "dpoy".to_string(),
+                name: "Giannis Antetokounmpo".to_string(),
+                age: Some(27),
+                team_abbreviation: "MIL".to_string(),
+                first_place_votes: Some(2.0),
+                pts_won: Some(18.0),
+                pts_max: Some(500.0),
+                share: Some(0.036),
+                winner: Some(true),
+                seas_id: 29918,
+                player_id: 4164,
+            },
+        ];
+        let all_stars = vec![
+            AllStarSelection {
+                name: "Giannis Antetokounmpo".to_string(),
+                team: "Team LeBron".to_string(),
+                lg: "NBA".to_string(),
+                season: 2022,
+                replaced: false,
+            },
+            AllStarSelection {
+                name: "Magic Johnson".to_string(),
+                team: "West".to_string(),
+                lg: "NBA".to_string(),
+                season: 1992,
+                replaced: false,
+            },
+        ];
+        let end_of_season_teams = vec![EndOfSeasonTeam {
+            season: 2022,
+            lg: "NBA".to_string(),
+            team_type: "All-Defense".to_string(),
+            number_tm: "1st".to_string(),
+            name: "Giannis Antetokounmpo".to_string(),
+            position: "NA".to_string(),
+            seas_id: 29918,
+            player_id: 4164,
+            team_abbreviation: "MIL".to_string(),
+            age: Some(27),
+        }];
+        let end_of_season_votes = vec![EndOfSeasonTeamVote {
+            season: 2022,
+            lg: "NBA".to_string(),
+            team_type: "All-NBA".to_string(),
+            number_tm: "1T".to_string(),
+            position: "F".to_string(),
+            name: "Giannis Antetokounmpo".to_string(),
+            age: Some(27),
+            team_abbreviation: "MIL".to_string(),
+            pts_won: Some(500.0),
+            pts_max: Some(500.0),
+            share: Some(1.0),
+            first_team_votes: Some(100),
+            second_team_votes: Some(0),
+            third_team_votes: Some(0),
+            seas_id: 29918,
+            player_id: 4164,
+        }];
+
+        let index = HonorsIndex::build(
+            &award_shares,
+            &all_stars,
+            &end_of_season_teams,
+            &end_of_season_votes,
+            &registry(),
+        );
+        let honors = index.get(4164, 2022);
+
+        assert_eq!(honors.mvp_share, Some(0.595));
+        assert_eq!(honors.dpoy_share, Some(0.036));
+        assert_eq!(honors.awards_won, vec!["dpoy".to_string()]);
+        assert_eq!(honors.all_defense_team, Some(1));
+        assert_eq!(honors.all_nba_team, None);
+        assert_eq!(honors.all_nba_vote_share, Some(1.0));
+        assert!(honors.all_star);
+
+        assert_eq!(index.get(4164, 2021), Honors::default());
+        assert_eq!(index.unresolved_all_stars.len(), 1);
+        assert_eq!(index.unresolved_all_stars[0].name, "Magic Johnson");
+    }
+
+    #[test]
+    fn test_team_number() {
+        assert_eq!(team_number("1st"), Some(1));
+        assert_eq!(team_number("3rd"), Some(3));
+        assert_eq!(team_number("2T"), Some(2));
+        assert_eq!(team_number("ORV"), None);
+    }
+}
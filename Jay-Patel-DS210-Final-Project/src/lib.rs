@@ -0,0 +1,13 @@
+pub mod data_loader;
+pub mod cache;
+pub mod analytics;
+pub mod graph;
+pub mod centrality;
+pub mod correlation;
+pub mod significance;
+pub mod data_structures;
+pub mod honors;
+pub mod registry;
+pub mod franchise;
+pub mod logistic;
+pub mod team_season;
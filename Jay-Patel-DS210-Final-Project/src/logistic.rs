@@ -0,0 +1,490 @@
+use crate::data_structures::{season_label, StatBasis, Team};
+use std::error::Error;
+use std::ops::RangeInclusive;
+use csv::Writer;
+
+const MAX_ITERATIONS: usize = 100;
+const TOLERANCE: f64 = 1e-10;
+
+type TeamFeature = fn(&Team, StatBasis) -> Option<f64>;
+
+// Team shooting features the playoff model is fitted on. Teams missing any of them are
+// left out of the fit. FG% is left out: it is nearly a mix of 2P% and 3P% weighted by the
+// 3PA rate, and fitting it next to them gives unstable coefficients. Opponent FG% is read
+// in the analysis' stat basis.
+pub const PLAYOFF_FEATURES: [(&str, TeamFeature); 4] = [
+    ("2P Percent", |team, _| team.two_point_percentage),
+    ("3P Percent", |team, _| team.three_point_percentage),
+    ("3PA Rate", |team, _| team.summary.as_ref()?.x3p_ar),
+    ("Opponent FG Percent", |team, basis| team.opponent_fg_percent(basis)),
+];
+
+// Features are standardized before fitting, so `estimate` is the change in log-odds for
+// one standard deviation of the feature. The intercept (mean 0, standard deviation 1) is
+// the log-odds of a team that is average in every feature.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Coefficient {
+    pub term: String,
+    pub mean: f64,
+    pub standard_deviation: f64,
+    pub estimate: f64,
+    pub standard_error: f64,
+}
+
+impl Coefficient {
+    pub fn odds_ratio(&self) -> f64 {
+        self.estimate.exp()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogisticModel {
+    pub coefficients: Vec<Coefficient>,
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+impl LogisticModel {
+    // Maximum likelihood by iteratively reweighted least squares (Newton's method). Each
+    // row of `rows` holds one value per name in `feature_names`. Fails when a feature has
+    // no spread, or when the outcomes are perfectly separated and the estimates run off to
+    // infinity.
+    pub fn fit(feature_names: &[&str], rows: &[Vec<f64>], outcomes: &[bool]) -> Result<Self, Box<dyn Error>> {
+        let n = rows.len() as f64;
+        let mut scales = Vec::new();
+        for (j, name) in feature_names.iter().enumerate() {
+            let mean = rows.iter().map(|row| row[j]).sum::<f64>() / n;
+            let variance = rows.iter().map(|row| (row[j] - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            if variance.is_nan() || variance <= 0.0 {
+                return Err(format!("'{}' has no spread over {} rows", name, rows.len()).into());
+            }
+            scales.push((mean, variance.sqrt()));
+        }
+        let design: Vec<Vec<f64>> = rows
+            .iter()
+            .map(|row| std::iter::once(1.0).chain(row.iter().zip(&scales).map(|(x, (mean, sd))| (x - mean) / sd)).collect())
+            .collect();
+
+        let mut beta = vec![0.0; feature_names.len() + 1];
+        let (mut iterations, mut converged) = (0, false);
+        while iterations < MAX_ITERATIONS && !converged {
+            iterations += 1;
+            let (information, gradient) = information_and_gradient(&design, outcomes, &beta);
+            let covariance =
+                invert(information).ok_or("the information matrix is singular; the outcomes may be perfectly separated")?;
+            let step: Vec<f64> = covariance.iter().map(|row| dot(row, &gradient)).collect();
+            for (b, s) in beta.iter_mut().zip(&step) {
+                *b += s;
+            }
+            if beta.iter().any(|b| !b.is_finite()) {
+                return Err("the estimates diverged; the outcomes may be perfectly separated".into());
+            }
+            converged = step.iter().all(|s| s.abs() < TOLERANCE);
+        }
+
+        let (information, _) = information_and_gradient(&design, outcomes, &beta);
+        let covariance = invert(information).ok_or("the information matrix is singular")?;
+        let terms = std::iter::once(("Intercept", (0.0, 1.0))).chain(feature_names.iter().copied().zip(scales));
+        let coefficients = terms
+            .enumerate()
+            .map(|(j, (term, (mean, standard_deviation)))| Coefficient {
+                term: term.to_string(),
+                mean,
+                standard_deviation,
+                estimate: beta[j],
+                standard_error: covariance[j][j].sqrt(),
+            })
+            .collect();
+
+        Ok(LogisticModel {
+            coefficients,
+            iterations,
+            converged,
+        })
+    }
+
+    // Probability of a positive outcome for one row of unstandardized feature values.
+    pub fn predict(&self, row: &[f64]) -> f64 {
+        let log_odds = self.coefficients[0].estimate
+            + self.coefficients[1..]
+                .iter()
+                .zip(row)
+                .map(|(c, x)| c.estimate * (x - c.mean) / c.standard_deviation)
+                .sum::<f64>();
+        sigmoid(log_odds)
+    }
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+// X'WX and X'(y - p) at `beta`, with W the diagonal of p(1 - p).
+fn information_and_gradient(design: &[Vec<f64>], outcomes: &[bool], beta: &[f64]) -> (Vec<Vec<f64>>, Vec<f64>) {
+    let k = beta.len();
+    let mut information = vec![vec![0.0; k]; k];
+    let mut gradient = vec![0.0; k];
+    for (row, &outcome) in design.iter().zip(outcomes) {
+        let p = sigmoid(dot(row, beta));
+        let weight = p * (1.0 - p);
+        for i in 0..k {
+            gradient[i] += row[i] * (outcome as u8 as f64 - p);
+            for j in 0..k {
+                information[i][j] += weight * row[i] * row[j];
+            }
+        }
+    }
+    (information, gradient)
+}
+
+// Gauss-Jordan elimination with partial pivoting. None when the matrix is singular.
+fn invert(mut matrix: Vec<Vec<f64>>) -> Option<Vec<Vec<f64>>> {
+    let k = matrix.len();
+    let mut inverse: Vec<Vec<f64>> = (0..k).map(|i| (0..k).map(|j| (i == j) as u8 as f64).collect()).collect();
+    for column in 0..k {
+        let pivot = (column..k).max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))?;
+        let pivot_size = matrix[pivot][column].abs();
+        if pivot_size.is_nan() || pivot_size <= 1e-12 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        inverse.swap(column, pivot);
+
+        let scale = matrix[column][column];
+        for j in 0..k {
+            matrix[column][j] /= scale;
+            inverse[column][j] /= scale;
+        }
+        for i in (0..k).filter(|&i| i != column) {
+            let factor = matrix[i][column];
+            for j in 0..k {
+                matrix[i][j] -= factor * matrix[column][j];
+                inverse[i][j] -= factor * inverse[column][j];
+            }
+        }
+    }
+    Some(inverse)
+}
+
+// Chance that a random positive scores above a random negative, ties counting half.
+// NaN without both outcomes.
+pub fn area_under_curve(probabilities: &[f64], outcomes: &[bool]) -> f64 {
+    let (mut pairs, mut wins) = (0.0, 0.0);
+    for (positive, _) in probabilities.iter().zip(outcomes).filter(|(_, &o)| o) {
+        for (negative, _) in probabilities.iter().zip(outcomes).filter(|(_, &o)| !o) {
+            pairs += 1.0;
+            wins += match positive.total_cmp(negative) {
+                std::cmp::Ordering::Greater => 1.0,
+                std::cmp::Ordering::Equal => 0.5,
+                std::cmp::Ordering::Less => 0.0,
+            };
+        }
+    }
+    wins / pairs
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConfusionMatrix {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub true_negatives: usize,
+    pub false_negatives: usize,
+}
+
+impl ConfusionMatrix {
+    // Predicts a positive when the probability is at least `threshold`.
+    pub fn from_predictions(probabilities: &[f64], outcomes: &[bool], threshold: f64) -> Self {
+        let mut matrix = ConfusionMatrix::default();
+        for (&p, &outcome) in probabilities.iter().zip(outcomes) {
+            match (p >= threshold, outcome) {
+                (true, true) => matrix.true_positives += 1,
+                (true, false) => matrix.false_positives += 1,
+                (false, false) => matrix.true_negatives += 1,
+                (false, true) => matrix.false_negatives += 1,
+            }
+        }
+        matrix
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        let correct = self.true_positives + self.true_negatives;
+        correct as f64 / (correct + self.false_positives + self.false_negatives) as f64
+    }
+}
+
+// How well a set of predicted probabilities sorts the teams.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModelFit {
+    pub auc: f64,
+    pub confusion_matrix: ConfusionMatrix,
+}
+
+impl ModelFit {
+    fn new(probabilities: &[f64], outcomes: &[bool]) -> Self {
+        ModelFit {
+            auc: area_under_curve(probabilities, outcomes),
+            confusion_matrix: ConfusionMatrix::from_predictions(probabilities, outcomes, 0.5),
+        }
+    }
+}
+
+// The playoff model for a set of team seasons. `training` judges it on the teams it was
+// fitted on, which flatters it; `leave_one_out` predicts each team from a model fitted
+// without it, and is None when one of those fits fails.
+pub struct PlayoffModel {
+    pub seasons: RangeInclusive<u32>,
+    pub teams: usize,
+    pub playoff_teams: usize,
+    pub model: LogisticModel,
+    pub training: ModelFit,
+    pub leave_one_out: Option<ModelFit>,
+}
+
+// Predicts `Team::playoffs` from `PLAYOFF_FEATURES`. A single season is only about thirty
+// teams, so estimates from one season are rough.
+pub fn fit_playoff_model<'a>(
+    teams: impl IntoIterator<Item = &'a Team>,
+    seasons: RangeInclusive<u32>,
+    basis: StatBasis,
+) -> Result<PlayoffModel, Box<dyn Error>> {
+    let (rows, outcomes): (Vec<Vec<f64>>, Vec<bool>) = teams
+        .into_iter()
+        .filter_map(|team| {
+            let row: Option<Vec<f64>> = PLAYOFF_FEATURES.iter().map(|(_, feature)| feature(team, basis)).collect();
+            Some((row?, team.playoffs))
+        })
+        .unzip();
+    let playoff_teams = outcomes.iter().filter(|&&o| o).count();
+    if playoff_teams == 0 || playoff_teams == outcomes.len() {
+        return Err(format!("{} teams with every feature, and they need both outcomes", outcomes.len()).into());
+    }
+
+    let feature_names: Vec<&str> = PLAYOFF_FEATURES.iter().map(|(name, _)| *name).collect();
+    let model = LogisticModel::fit(&feature_names, &rows, &outcomes)?;
+    let probabilities: Vec<f64> = rows.iter().map(|row| model.predict(row)).collect();
+    let held_out: Option<Vec<f64>> = (0..rows.len())
+        .map(|i| {
+            let (mut rest, mut rest_outcomes) = (rows.clone(), outcomes.clone());
+            rest.remove(i);
+            rest_outcomes.remove(i);
+            let model = LogisticModel::fit(&feature_names, &rest, &rest_outcomes).ok()?;
+            Some(model.predict(&rows[i]))
+        })
+        .collect();
+
+    Ok(PlayoffModel {
+        seasons,
+        teams: rows.len(),
+        playoff_teams,
+        training: ModelFit::new(&probabilities, &outcomes),
+        leave_one_out: held_out.map(|probabilities| ModelFit::new(&probabilities, &outcomes)),
+        model,
+    })
+}
+
+// One row per coefficient in `coefficients_path`, and the fit summary in `fit_path`. A
+// model that could not be fitted (None) leaves both files with only their headers.
+pub fn write_playoff_model_to_csv(
+    playoff_model: Option<&PlayoffModel>,
+    coefficients_path: &str,
+    fit_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut writer = Writer::from_path(coefficients_path)?;
+    writer.write_record([
+        "Season",
+        "Term",
+        "Feature Mean",
+        "Feature SD",
+        "Coefficient",
+        "Standard Error",
+        "Odds Ratio",
+    ])?;
+    if let Some(playoff_model) = playoff_model {
+        for coefficient in &playoff_model.model.coefficients {
+            writer.write_record([
+                season_label(&playoff_model.seasons),
+                coefficient.term.clone(),
+                coefficient.mean.to_string(),
+                coefficient.standard_deviation.to_string(),
+                coefficient.estimate.to_string(),
+                coefficient.standard_error.to_string(),
+                coefficient.odds_ratio().to_string(),
+            ])?;
+        }
+    }
+    writer.flush()?;
+
+    // The training figures and then the leave-one-out ones, which are empty when missing.
+    let fit_columns = ["AUC", "Accuracy", "True Positives", "False Positives", "True Negatives", "False Negatives"];
+    let fit_record = |fit: Option<&ModelFit>| -> Vec<String> {
+        let Some(fit) = fit else {
+            return vec![String::new(); fit_columns.len()];
+        };
+        let matrix = &fit.confusion_matrix;
+        vec![
+            fit.auc.to_string(),
+            matrix.accuracy().to_string(),
+            matrix.true_positives.to_string(),
+            matrix.false_positives.to_string(),
+            matrix.true_negatives.to_string(),
+            matrix.false_negatives.to_string(),
+        ]
+    };
+
+    let mut writer = Writer::from_path(fit_path)?;
+    let mut headers: Vec<String> =
+        ["Season", "Teams", "Playoff Teams", "Iterations", "Converged"].iter().map(|h| h.to_string()).collect();
+    for prefix in ["Training", "Leave-One-Out"] {
+        headers.extend(fit_columns.iter().map(|column| format!("{} {}", prefix, column)));
+    }
+    writer.write_record(&headers)?;
+    if let Some(playoff_model) = playoff_model {
+        let mut record = vec![
+            season_label(&playoff_model.seasons),
+            playoff_model.teams.to_string(),
+            playoff_model.playoff_teams.to_string(),
+            playoff_model.model.iterations.to_string(),
+            playoff_model.model.converged.to_string(),
+        ];
+        record.extend(fit_record(Some(&playoff_model.training)));
+        record.extend(fit_record(playoff_model.leave_one_out.as_ref()));
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_structures::{OpponentShooting, TeamSummary};
+
+    #[test]
+    fn test_fit_matches_odds_ratio() {
+        // One binary feature: 2 of 6 positive at 0 and 6 of 9 at 1, an odds ratio of 4.
+        let rows: Vec<Vec<f64>> = [0.0; 6].iter().chain(&[1.0; 9]).map(|&x| vec![x]).collect();
+        let outcomes: Vec<bool> = [true, true, false, false, false, false]
+            .into_iter()
+            .chain([true, true, true, true, true, true, false, false, false])
+            .collect();
+
+        let model = LogisticModel::fit(&["x"], &rows, &outcomes).unwrap();
+        let slope = &model.coefficients[1];
+
+        assert!(model.converged);
+        assert_eq!(model.coefficients[0].term, "Intercept");
+        assert!((slope.estimate / slope.standard_deviation - 4f64.ln()).abs() < 1e-8);
+        // Woolf's standard error of the log odds ratio.
+        let standard_error = (1.0 / 2.0 + 1.0 / 4.0 + 1.0 / 6.0 + 1.0 / 3.0_f64).sqrt();
+        assert!((slope.standard_error / slope.standard_deviation - standard_error).abs() < 1e-8);
+        assert!((model.predict(&[0.0]) - 2.0 / 6.0).abs() < 1e-8);
+        assert!((model.predict(&[1.0]) - 6.0 / 9.0).abs() < 1e-8);
+    }
+
+    #[test]
+    fn test_fit_failures() {
+        let rows: Vec<Vec<f64>> = (0..6).map(|i| vec![i as f64]).collect();
+        let separated = [false, false, false, true, true, true];
+        assert!(LogisticModel::fit(&["x"], &rows, &separated).is_err());
+
+        let constant = vec![vec![1.0]; 6];
+        let outcomes = [false, true, false, true, true, false];
+        assert!(LogisticModel::fit(&["x"], &constant, &outcomes).unwrap_err().to_string().contains("no spread"));
+    }
+
+    #[test]
+    fn test_invert() {
+        let inverse = invert(vec![vec![0.0, 2.0], vec![4.0, 0.0]]).unwrap();
+        assert_eq!(inverse, vec![vec![0.0, 0.25], vec![0.5, 0.0]]);
+        assert!(invert(vec![vec![1.0, 2.0], vec![2.0, 4.0]]).is_none());
+    }
+
+    #[test]
+    fn test_area_under_curve() {
+        assert_eq!(area_under_curve(&[0.9, 0.8, 0.3, 0.4], &[true, false, true, false]), 0.5);
+        assert_eq!(area_under_curve(&[0.9, 0.2, 0.6], &[true, false, true]), 1.0);
+        assert_eq!(area_under_curve(&[0.5, 0.5], &[true, false]), 0.5);
+        assert!(area_under_curve(&[0.5], &[true]).is_nan());
+    }
+
+    #[test]
+    fn test_confusion_matrix() {
+        let matrix = ConfusionMatrix::from_predictions(&[0.9, 0.6, 0.4, 0.2, 0.5], &[true, false, true, false, true], 0.5);
+
+        assert_eq!(
+            matrix,
+            ConfusionMatrix {
+                true_positives: 2,
+                false_positives: 1,
+                true_negatives: 1,
+                false_negatives: 1,
+            }
+        );
+        assert_eq!(matrix.accuracy(), 0.6);
+    }
+
+    #[test]
+    fn test_fit_playoff_model() {
+        let team = |i: u32, playoffs: bool| {
+            let x = i as f64;
+            Team {
+                abbreviation: format!("T{}", i),
+                name: format!("Team {}", i),
+                playoffs,
+                two_point_percentage: Some(0.50 + 0.003 * ((x * 7.0) % 5.0)),
+                three_point_percentage: Some(0.33 + 0.002 * ((x * 3.0) % 7.0)),
+                opponent: Some(OpponentShooting {
+                    fg_percent: Some(0.47 - 0.001 * ((x * 5.0) % 9.0)),
+                    ..Default::default()
+                }),
+                ..Team::test_fixture()
+            }
+        };
+        let mut teams: Vec<Team> = (0..20).map(|i| team(i, (i * 7) % 10 < 6)).collect();
+        // Without a summary there is no 3PA rate, so no team has every feature.
+        assert!(fit_playoff_model(&teams, 2022..=2022, StatBasis::PerGame).is_err());
+
+        for (i, team) in teams.iter_mut().enumerate() {
+            team.summary = Some(TeamSummary {
+                x3p_ar: Some(0.30 + 0.01 * ((i * 3) % 11) as f64),
+                ..Default::default()
+            });
+        }
+        teams.push(team(20, true));
+
+        let playoff_model = fit_playoff_model(&teams, 2022..=2022, StatBasis::PerGame).unwrap();
+        let matrix = playoff_model.training.confusion_matrix;
+        let held_out = playoff_model.leave_one_out.unwrap();
+
+        assert_eq!(playoff_model.teams, 20);
+        assert_eq!(playoff_model.playoff_teams, 12);
+        let terms: Vec<&str> = playoff_model.model.coefficients.iter().map(|c| c.term.as_str()).collect();
+        assert_eq!(terms, ["Intercept", "2P Percent", "3P Percent", "3PA Rate", "Opponent FG Percent"]);
+        assert_eq!(matrix.true_positives + matrix.false_positives + matrix.true_negatives + matrix.false_negatives, 20);
+        assert!(playoff_model.training.auc > 0.5 && playoff_model.training.auc <= 1.0);
+        // Teams predicted by models that never saw them are sorted worse.
+        assert!(held_out.auc < playoff_model.training.auc);
+        assert_eq!(held_out.confusion_matrix.true_positives + held_out.confusion_matrix.false_negatives, 12);
+
+        let (coefficients_path, fit_path) = ("Playoff Model Test.csv", "Playoff Model Fit Test.csv");
+        write_playoff_model_to_csv(Some(&playoff_model), coefficients_path, fit_path).unwrap();
+        let coefficients = std::fs::read_to_string(coefficients_path).unwrap();
+        let fit = std::fs::read_to_string(fit_path).unwrap();
+        write_playoff_model_to_csv(None, coefficients_path, fit_path).unwrap();
+        let empty = std::fs::read_to_string(fit_path).unwrap();
+        std::fs::remove_file(coefficients_path).unwrap();
+        std::fs::remove_file(fit_path).unwrap();
+
+        assert_eq!(coefficients.lines().count(), PLAYOFF_FEATURES.len() + 2);
+        assert!(coefficients.lines().nth(1).unwrap().starts_with("2022,Intercept,0,1,"));
+        assert!(fit.lines().next().unwrap().contains(",Training AUC,Training Accuracy,"));
+        assert!(fit.lines().next().unwrap().ends_with(",Leave-One-Out True Negatives,Leave-One-Out False Negatives"));
+        assert!(fit.lines().nth(1).unwrap().starts_with("2022,20,12,"));
+        assert_eq!(fit.lines().nth(1).unwrap().split(',').count(), 17);
+        assert_eq!(empty.lines().count(), 1);
+    }
+}
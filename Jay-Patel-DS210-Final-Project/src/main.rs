@@ -1,95 +1,477 @@
-mod data_loader;
-mod analytics;
-mod graph;
-mod centrality;
-mod data_structures;
-
-use data_loader::{load_player_data, load_team_data};
-use data_structures::{Player, Team, MergedData};
-use analytics::{
-    correlate_statistics, analyze_playoff_correlation, write_correlations_to_csv,
-    CorrelationResult, PlayoffCorrelationResults,
+use nba_analysis::data_loader::{
+    estimate_shot_attempts, load_all_star_selections, load_award_shares, load_end_of_season_team_votes, load_end_of_season_teams,
+    load_player_career_info, load_player_per100, load_player_season_info, load_player_totals, load_team_abbreviations,
+    load_team_data, load_team_summaries, load_opponent_stats, load_team_box_scores, merge_opponent_stats, merge_team_summaries,
+    load_play_by_play, stream_player_data, LoadOptions, MultiTeamPolicy, RowFilter,
 };
-use centrality::calculate_centrality;
-use graph::Graph;
+use nba_analysis::data_structures::{AnalysisScope, LeagueFilter, MergedData, Qualifier, StatBasis, Team};
+use nba_analysis::analytics::{
+    analyze_outcome_correlation, analyze_playoff_correlation, correlate_player_careers, shooting_differentials,
+    adjust_p_values, write_correlations_to_csv, write_differentials_to_csv, CorrelationResult, TeamOutcome,
+};
+use nba_analysis::centrality::calculate_centrality;
+use nba_analysis::correlation::CorrelationMethod;
+use nba_analysis::franchise::{filter_data_by_season, team_key, FranchiseRegistry, PlayersByTeam};
+use nba_analysis::graph::Graph;
+use nba_analysis::logistic::{fit_playoff_model, write_playoff_model_to_csv};
+use nba_analysis::honors::HonorsIndex;
+use nba_analysis::registry::PlayerRegistry;
+use nba_analysis::significance::{BootstrapOptions, CorrelationOptions, PValueAdjustment};
+use nba_analysis::team_season::TeamSeasonTable;
+use csv::{Reader, Writer};
 use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
 
-fn filter_data_by_season(
-    player_data: &[Player],
-    team_data: &[Team],
-    season: u32,
-) -> (HashMap<String, Vec<Player>>, Vec<Team>) {
-    let mut players_by_team: HashMap<String, Vec<Player>> = HashMap::new();
-    let filtered_teams: Vec<Team> = team_data
-        .iter()
-        .filter(|t| t.season == season)
-        .cloned()
-        .collect();
-
-    for player in player_data.iter().filter(|p| p.season == season) {
-        players_by_team
-            .entry(player.team_abbreviation.clone())
-            .or_insert_with(Vec::new)
-            .push(player.clone());
-    }
+// Written by every run, in this order: centrality, player versus team shooting, all
+// players, player careers, and the playoff model's coefficients and fit.
+const OUTPUT_FILES: [&str; 6] = [
+    "Centrality Scores.csv",
+    "Player Shooting Stats Analytics.csv",
+    "Players' Contribution To Team.csv",
+    "Player Career Correlations.csv",
+    "Playoff Model.csv",
+    "Playoff Model Fit.csv",
+];
+const BATCH_OUTPUT_DIR: &str = "out";
 
-    (players_by_team, filtered_teams)
+// Everything the analyses read, loaded once for every season of a run.
+struct PipelineData {
+    players_by_team: PlayersByTeam,
+    teams: Vec<Team>,
+    season_games: HashMap<u32, u32>,
+    franchises: FranchiseRegistry,
+    registry: PlayerRegistry,
+    honors_index: HonorsIndex,
 }
 
-use petgraph::visit::EdgeRef;
-
-fn main() {
-    let player_data = load_player_data("NBA Stats (1947-Present)/Player Shooting.csv").unwrap();
-    let team_data = load_team_data("NBA Stats (1947-Present)/Team Stats Per Game.csv").unwrap();
-
-    let season = 2022;
-    let (players_by_team, filtered_teams) = filter_data_by_season(&player_data, &team_data, season);
-
-    let mut correlation_merged_data = Vec::new();
-    for team in &filtered_teams {
-        if let Some(team_players) = players_by_team.get(&team.abbreviation) {
-            let player = &team_players[0];
-            correlation_merged_data.push(MergedData {
-                player: player.clone(),
-                team: team.clone(),
-            });
-        }
+impl PipelineData {
+    // Basketball-Reference's leaderboard minimums for every loaded season, prorated to the
+    // length of that season.
+    fn season_qualifiers(&self) -> HashMap<u32, Qualifier> {
+        self.season_games
+            .iter()
+            .map(|(&season, &games)| (season, Qualifier::basketball_reference(games)))
+            .collect()
     }
 
-    let mut centrality_merged_data = Vec::new();
-    for team in &filtered_teams {
-        if let Some(team_players) = players_by_team.get(&team.abbreviation) {
-            for player in team_players {
-                centrality_merged_data.push(MergedData {
+    // Every player of the teams `keep` accepts, paired with the team and the player's honors.
+    fn merged_data(&self, keep: impl Fn(&Team) -> bool) -> Vec<MergedData> {
+        let mut merged_data = Vec::new();
+        for team in self.teams.iter().filter(|t| keep(t)) {
+            for player in self.players_by_team.get(&team_key(team, &self.franchises)).into_iter().flatten() {
+                merged_data.push(MergedData {
                     player: player.clone(),
                     team: team.clone(),
+                    honors: self.honors_index.get(player.id, player.season),
                 });
             }
         }
+        merged_data
+    }
+}
+
+// Only the seasons and leagues of `scope` are parsed from the player and team files, and
+// players are streamed straight into their teams. `multi_team_policy` decides how traded
+// players' seasons are counted. Parsed files are kept in ".cache" and reused until the
+// CSV changes.
+fn load_pipeline_data(scope: &AnalysisScope, multi_team_policy: MultiTeamPolicy) -> PipelineData {
+    let load_options = LoadOptions {
+        multi_team_policy,
+        cache_dir: Some(".cache".to_string()),
+        ..Default::default()
+    };
+    let season_options = LoadOptions {
+        filter: RowFilter {
+            seasons: Some(scope.seasons.clone()),
+            leagues: scope.leagues.clone(),
+            ..Default::default()
+        },
+        ..load_options.clone()
+    };
+    let mut player_stream =
+        stream_player_data("NBA Stats (1947-Present)/Player Shooting.csv", &season_options).unwrap();
+    let (mut team_data, team_report) =
+        load_team_data("NBA Stats (1947-Present)/Team Stats Per Game.csv", &season_options).unwrap();
+    let (team_summary_data, team_summary_report) =
+        load_team_summaries("NBA Stats (1947-Present)/Team Summaries.csv", &load_options).unwrap();
+    let (per_100_data, per_100_report) =
+        load_player_per100("NBA Stats (1947-Present)/Per 100 Poss.csv", &season_options).unwrap();
+    let (opponent_per_game_data, opponent_per_game_report) = load_opponent_stats(
+        "NBA Stats (1947-Present)/Opponent Stats Per Game.csv",
+        StatBasis::PerGame,
+        &load_options,
+    )
+    .unwrap();
+    let (opponent_per_100_data, opponent_per_100_report) = load_opponent_stats(
+        "NBA Stats (1947-Present)/Opponent Stats Per 100 Poss.csv",
+        StatBasis::Per100Poss,
+        &load_options,
+    )
+    .unwrap();
+    let (opponent_totals_data, opponent_totals_report) =
+        load_opponent_stats("NBA Stats (1947-Present)/Opponent Totals.csv", StatBasis::Totals, &load_options).unwrap();
+    let (team_per_game_data, team_per_game_report) = load_team_box_scores(
+        "NBA Stats (1947-Present)/Team Stats Per Game.csv",
+        StatBasis::PerGame,
+        &load_options,
+    )
+    .unwrap();
+    let (team_per_100_data, team_per_100_report) = load_team_box_scores(
+        "NBA Stats (1947-Present)/Team Stats Per 100 Poss.csv",
+        StatBasis::Per100Poss,
+        &load_options,
+    )
+    .unwrap();
+    let (team_totals_data, team_totals_report) =
+        load_team_box_scores("NBA Stats (1947-Present)/Team Totals.csv", StatBasis::Totals, &load_options).unwrap();
+    let (team_abbreviation_data, team_abbreviation_report) =
+        load_team_abbreviations("NBA Stats (1947-Present)/Team Abbrev.csv", &load_options).unwrap();
+    let (career_data, career_report) =
+        load_player_career_info("NBA Stats (1947-Present)/Player Career Info.csv", &load_options).unwrap();
+    let (season_info_data, season_info_report) =
+        load_player_season_info("NBA Stats (1947-Present)/Player Season Info.csv", &load_options).unwrap();
+    let (play_by_play_data, play_by_play_report) =
+        load_play_by_play("NBA Stats (1947-Present)/Player Play By Play.csv", &load_options).unwrap();
+    let (award_share_data, award_share_report) =
+        load_award_shares("NBA Stats (1947-Present)/Player Award Shares.csv", &load_options).unwrap();
+    let (all_star_data, all_star_report) =
+        load_all_star_selections("NBA Stats (1947-Present)/All-Star Selections.csv", &load_options).unwrap();
+    let (end_of_season_team_data, end_of_season_team_report) =
+        load_end_of_season_teams("NBA Stats (1947-Present)/End of Season Teams.csv", &load_options).unwrap();
+    let (end_of_season_vote_data, end_of_season_vote_report) = load_end_of_season_team_votes(
+        "NBA Stats (1947-Present)/End of Season Teams (Voting).csv",
+        &load_options,
+    )
+    .unwrap();
+    for report in [
+        &team_report,
+        &team_summary_report,
+        &per_100_report,
+        &opponent_per_game_report,
+        &opponent_per_100_report,
+        &opponent_totals_report,
+        &team_per_game_report,
+        &team_per_100_report,
+        &team_totals_report,
+        &team_abbreviation_report,
+        &career_report,
+        &season_info_report,
+        &play_by_play_report,
+        &award_share_report,
+        &all_star_report,
+        &end_of_season_team_report,
+        &end_of_season_vote_report,
+    ] {
+        eprintln!("{}", report);
+    }
+
+    // Attempt counts come from the player totals; without that file they are estimated.
+    let shot_attempts = match load_player_totals("NBA Stats (1947-Present)/Player Totals.csv", &season_options) {
+        Ok((totals_data, totals_report)) => {
+            eprintln!("{}", totals_report);
+            totals_data.iter().map(|t| (t.seas_id, t.attempts())).collect()
+        }
+        Err(e) => {
+            eprintln!("Player Totals.csv not loaded ({}); estimating attempts from Per 100 Poss.csv", e);
+            estimate_shot_attempts(&per_100_data, &team_summary_data)
+        }
+    };
+    let season_games = team_summary_data
+        .iter()
+        .filter_map(|s| Some((s.season, s.wins? + s.losses?)))
+        .fold(HashMap::new(), |mut season_games, (season, games)| {
+            let longest = season_games.entry(season).or_insert(games);
+            *longest = games.max(*longest);
+            season_games
+        });
+
+    merge_team_summaries(&mut team_data, team_summary_data);
+    merge_opponent_stats(&mut team_data, &opponent_per_game_data);
+
+    let team_seasons = TeamSeasonTable::build(team_per_game_data, team_per_100_data, team_totals_data);
+    let consistency_issues = team_seasons.check_consistency();
+    if !consistency_issues.is_empty() {
+        eprintln!(
+            "{} team season values disagree between per game, per 100 and totals files",
+            consistency_issues.len()
+        );
     }
+    team_seasons.merge_into(&mut team_data);
+    TeamSeasonTable::build(opponent_per_game_data, opponent_per_100_data, opponent_totals_data)
+        .merge_opponents_into(&mut team_data);
+
+    let franchises = FranchiseRegistry::new(team_abbreviation_data);
+    let unresolved_teams = team_data
+        .iter()
+        .filter(|t| t.abbreviation != "NA" && franchises.franchise_id(t.season, &t.abbreviation).is_none())
+        .count();
+    if unresolved_teams > 0 {
+        eprintln!("{} team seasons could not be matched to a franchise", unresolved_teams);
+    }
+
+    let registry = PlayerRegistry::new(career_data, season_info_data, play_by_play_data);
+    let honors_index = HonorsIndex::build(
+        &award_share_data,
+        &all_star_data,
+        &end_of_season_team_data,
+        &end_of_season_vote_data,
+        &registry,
+    );
+    if !honors_index.unresolved_all_stars.is_empty() {
+        eprintln!(
+            "{} All-Star selections could not be matched to a player",
+            honors_index.unresolved_all_stars.len()
+        );
+    }
+
+    let players = player_stream.by_ref().map(Result::unwrap).map(|mut player| {
+        player.attempts = shot_attempts.get(&player.seas_id).cloned().unwrap_or_default();
+        player
+    });
+    let (players_by_team, teams) = filter_data_by_season(players, team_data, scope, &franchises);
+    eprintln!("{}", player_stream.report());
+
+    PipelineData {
+        players_by_team,
+        teams,
+        season_games,
+        franchises,
+        registry,
+        honors_index,
+    }
+}
+
+// Runs the graph and correlation steps over `scope` and writes the output files into
+// `out_dir`.
+fn run_analysis(
+    data: &PipelineData,
+    scope: &AnalysisScope,
+    options: &CorrelationOptions,
+    out_dir: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let merged_data = data.merged_data(|team| scope.includes(team.season, &team.lg));
+    // Careers reach outside the analysed seasons.
+    let career_merged_data = data.merged_data(|team| scope.leagues.matches(&team.lg));
 
     let mut graph = Graph::new();
-    graph.construct_from_data(&centrality_merged_data);
+    graph.construct_from_data(&merged_data, scope, &data.franchises);
 
     let mut node_labels = HashMap::new();
-    for data in &centrality_merged_data {
+    for data in &merged_data {
         node_labels.insert(data.player.id, format!("{} (Player)", data.player.name));
         node_labels.insert(data.team.abbreviation.as_bytes().iter().map(|&b| b as u32).sum(), format!("{} (Team)", data.team.abbreviation));
     }
 
-    let _ = calculate_centrality(&graph, &node_labels, "Centrality Scores.csv").unwrap();
+    let player_metadata: HashMap<_, _> = merged_data
+        .iter()
+        .map(|d| ((d.player.id, d.player.season), data.registry.metadata(d.player.id, d.player.seas_id)))
+        .collect();
 
-    let player_analytics = correlate_statistics(&correlation_merged_data);
-    let playoff_correlation = analyze_playoff_correlation(&correlation_merged_data);
+    let output_path = |file_name: &str| out_dir.join(file_name).to_string_lossy().into_owned();
 
-    write_correlations_to_csv(&player_analytics, "Player Shooting Stats Analytics.csv", true).unwrap();
+    calculate_centrality(&graph, &node_labels, &player_metadata, &output_path(OUTPUT_FILES[0]))?;
 
-    let all_players_playoffs: Vec<CorrelationResult> = playoff_correlation
-        .all_players_correlation
-        .values()
-        .cloned()
-        .collect();
-    write_correlations_to_csv(&all_players_playoffs, "Players' Contribution To Team.csv", false).unwrap();
+    let differentials = shooting_differentials(&merged_data, scope);
+    let playoff_correlation = analyze_playoff_correlation(&merged_data, scope, options);
+
+    write_differentials_to_csv(&differentials, &output_path(OUTPUT_FILES[1]), Some(&player_metadata))?;
+
+    let mut all_players_playoffs: Vec<CorrelationResult> = playoff_correlation.all_players_correlation;
+    for outcome in [TeamOutcome::Points(scope.team_basis), TeamOutcome::WinPercentage, TeamOutcome::NetRating] {
+        let outcome_correlation = analyze_outcome_correlation(&merged_data, outcome, scope, options);
+        all_players_playoffs.extend(outcome_correlation.all_players_correlation);
+    }
+    adjust_p_values(&mut all_players_playoffs, options);
+    write_correlations_to_csv(&all_players_playoffs, &output_path(OUTPUT_FILES[2]), false, None)?;
+
+    let mut career_correlations = correlate_player_careers(&career_merged_data, scope, options, &data.franchises);
+    adjust_p_values(&mut career_correlations, options);
+    write_correlations_to_csv(&career_correlations, &output_path(OUTPUT_FILES[3]), true, None)?;
+
+    // A season the model cannot be fitted to still gets both files, with only headers.
+    let playoff_model = fit_playoff_model(
+        data.teams.iter().filter(|t| scope.includes(t.season, &t.lg)),
+        scope.seasons.clone(),
+        scope.team_basis,
+    );
+    if let Err(e) = &playoff_model {
+        eprintln!("Playoff model for {} not fitted: {}", scope.season_label(), e);
+    }
+    write_playoff_model_to_csv(playoff_model.as_ref().ok(), &output_path(OUTPUT_FILES[4]), &output_path(OUTPUT_FILES[5]))?;
+    Ok(())
+}
+
+// Runs every season of `seasons` on its own with the leagues and team basis of `base`,
+// spread over the available cores, writing
+// `out_dir/<season>/<output file>`. Each output file is then also combined over all seasons
+// into `out_dir/<output file>`; the files already start with a season column. Returns the
+// seasons that failed with their errors.
+fn run_batch(
+    data: &PipelineData,
+    seasons: RangeInclusive<u32>,
+    base: &AnalysisScope,
+    options: &CorrelationOptions,
+    out_dir: &Path,
+) -> Result<Vec<(u32, String)>, Box<dyn Error>> {
+    let seasons: Vec<u32> = seasons.collect();
+    let next_season = AtomicUsize::new(0);
+    let failures = Mutex::new(Vec::new());
+    let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(seasons.len());
+
+    thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| {
+                while let Some(&season) = seasons.get(next_season.fetch_add(1, Ordering::Relaxed)) {
+                    let scope = AnalysisScope {
+                        seasons: season..=season,
+                        ..base.clone()
+                    };
+                    let season_dir = out_dir.join(season.to_string());
+                    let result = fs::create_dir_all(&season_dir)
+                        .map_err(|e| e.to_string())
+                        .and_then(|_| run_analysis(data, &scope, options, &season_dir).map_err(|e| e.to_string()));
+                    if let Err(e) = result {
+                        failures.lock().unwrap().push((season, e));
+                    }
+                }
+            });
+        }
+    });
+
+    let mut failures = failures.into_inner()?;
+    failures.sort();
+    let finished: Vec<u32> = seasons.into_iter().filter(|s| failures.iter().all(|(f, _)| f != s)).collect();
+    for file_name in OUTPUT_FILES {
+        let season_files: Vec<PathBuf> = finished.iter().map(|s| out_dir.join(s.to_string()).join(file_name)).collect();
+        combine_season_files(&season_files, &out_dir.join(file_name))?;
+    }
+    Ok(failures)
+}
+
+// Writes the header of the first file once, followed by the rows of every file in order.
+fn combine_season_files(season_files: &[PathBuf], combined_path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut writer = Writer::from_path(combined_path)?;
+    for (i, season_file) in season_files.iter().enumerate() {
+        let mut reader = Reader::from_path(season_file)?;
+        if i == 0 {
+            writer.write_record(reader.headers()?)?;
+        }
+        for record in reader.records() {
+            writer.write_record(&record?)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// "2022" or "1997-2024".
+fn parse_season_range(text: &str) -> Result<RangeInclusive<u32>, Box<dyn Error>> {
+    let (start, end) = text.split_once('-').unwrap_or((text, text));
+    let (start, end): (u32, u32) = (start.trim().parse()?, end.trim().parse()?);
+    if start > end {
+        return Err(format!("season range '{}' ends before it starts", text).into());
+    }
+    Ok(start..=end)
+}
+
+// The value following `flag` on the command line.
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let i = args.iter().position(|a| a == flag)?;
+    Some(args.get(i + 1).map_or("", String::as_str))
+}
+
+// `cargo run --release` analyses the 2022 season into the project folder. With
+// `-- --batch 1997-2024` every season of the range is analysed into "out", and
+// `-- --method spearman` (or kendall) replaces the Pearson correlations. `-- --bootstrap 1000`
+// adds bootstrap intervals from that many resamples, seeded with `--seed` (default 0).
+// `-- --adjust holm` (or bonferroni, bh) corrects the p-values of each output file for
+// the number of correlations in it. `-- --multi-team weighted` counts a traded player's
+// stints by their share of the season's minutes, and `total` uses their combined season
+// credited to their main team; the default, `stints`, counts every stint in full.
+// `-- --team-basis per-100` (or totals) reads team points per 100 possessions instead of
+// per game, in the outcome correlations and the graph. Every player season counts unless
+// `-- --qualify` is given, which only counts percentages of players who meet
+// Basketball-Reference's leaderboard minimums for their season. `-- --out results` writes
+// the output files into "results" instead of the project folder (or "out" in batch mode).
+// Minimums given on the command line. They replace the matching minimums of every season
+// with "--qualify", and apply unprorated to every season without it.
+fn parse_minimums(args: &[String]) -> Qualifier {
+    fn minimum<T: FromStr>(args: &[String], flag: &str) -> Option<T> {
+        flag_value(args, flag).map(|value| value.parse().unwrap_or_else(|_| panic!("{} takes a number", flag)))
+    }
+    Qualifier {
+        min_games: minimum(args, "--min-games"),
+        min_minutes: minimum(args, "--min-minutes"),
+        min_fga: minimum(args, "--min-fga"),
+        min_x2pa: minimum(args, "--min-2pa"),
+        min_x3pa: minimum(args, "--min-3pa"),
+        min_fg_made: minimum(args, "--min-fg"),
+        min_x3p_made: minimum(args, "--min-3p"),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    run(&args);
+}
+
+fn run(args: &[String]) {
+    let batch_seasons = flag_value(args, "--batch").map(|range| parse_season_range(range).unwrap());
+    let method = flag_value(args, "--method")
+        .map(|name| CorrelationMethod::parse(name).unwrap_or_else(|| panic!("unknown correlation method '{}'", name)))
+        .unwrap_or_default();
+    let bootstrap = flag_value(args, "--bootstrap").map(|resamples| BootstrapOptions {
+        resamples: resamples.parse().expect("--bootstrap takes a number of resamples"),
+        seed: flag_value(args, "--seed").map_or(0, |seed| seed.parse().expect("--seed takes a number")),
+    });
+    let adjustment = flag_value(args, "--adjust")
+        .map(|name| PValueAdjustment::parse(name).unwrap_or_else(|| panic!("unknown p-value adjustment '{}'", name)))
+        .unwrap_or_default();
+    let options = CorrelationOptions { method, bootstrap, adjustment, ..Default::default() };
+    let multi_team_policy = flag_value(args, "--multi-team")
+        .map(|name| MultiTeamPolicy::parse(name).unwrap_or_else(|| panic!("unknown multi-team policy '{}'", name)))
+        .unwrap_or_default();
+    let team_basis = flag_value(args, "--team-basis")
+        .map(|name| StatBasis::parse(name).unwrap_or_else(|| panic!("unknown team stat basis '{}'", name)))
+        .unwrap_or_default();
+
+    // Every step runs over these seasons and leagues. Every season of the leagues is loaded,
+    // since the career correlations look past the analysed seasons.
+    let qualify = args.iter().any(|a| a == "--qualify");
+    let minimums = parse_minimums(args);
+    let mut scope = AnalysisScope {
+        seasons: batch_seasons.clone().unwrap_or(2022..=2022),
+        leagues: LeagueFilter::only(&["NBA"]),
+        qualifier: minimums.clone(),
+        team_basis,
+        ..Default::default()
+    };
+    let data =
+        load_pipeline_data(&AnalysisScope { leagues: scope.leagues.clone(), ..Default::default() }, multi_team_policy);
+    if qualify {
+        scope.season_qualifiers =
+            data.season_qualifiers().into_iter().map(|(season, q)| (season, q.overridden_by(&minimums))).collect();
+    }
+
+    let default_out_dir = if batch_seasons.is_some() { BATCH_OUTPUT_DIR } else { "." };
+    let out_dir = Path::new(flag_value(args, "--out").unwrap_or(default_out_dir));
+    fs::create_dir_all(out_dir).unwrap();
+    match batch_seasons {
+        Some(seasons) => {
+            let failures = run_batch(&data, seasons, &scope, &options, out_dir).unwrap();
+            for (season, e) in &failures {
+                eprintln!("Season {} failed: {}", season, e);
+            }
+        }
+        None => run_analysis(&data, &scope, &options, out_dir).unwrap(),
+    }
 }
 
 #[cfg(test)]
@@ -97,69 +479,85 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_filter_data_by_season() {
-        let player_data = vec![
-            Player {
-                id: 1,
-                name: "Player 1".to_string(),
-                team_abbreviation: "TEA".to_string(),
-                season: 2022,
-                fg_percent: 0.5,
-                fg_percent_from_x2p_range: 0.6,
-                fg_percent_from_x3p_range: 0.4,
-            },
-            Player {
-                id: 2,
-                name: "Player 2".to_string(),
-                team_abbreviation: "TEA".to_string(),
-                season: 2021,
-                fg_percent: 0.6,
-                fg_percent_from_x2p_range: 0.7,
-                fg_percent_from_x3p_range: 0.5,
-            },
-        ];
-
-        let team_data = vec![
-            Team {
-                abbreviation: "TEA".to_string(),
-                name: "Team A".to_string(),
-                season: 2022,
-                playoffs: true,
-                fg_percentage: 0.55,
-                two_point_percentage: 0.65,
-                three_point_percentage: 0.45,
-                points_per_game: 100.0,
-            },
-            Team {
-                abbreviation: "TEB".to_string(),
-                name: "Team B".to_string(),
-                season: 2022,
-                playoffs: false,
-                fg_percentage: 0.50,
-                two_point_percentage: 0.60,
-                three_point_percentage: 0.40,
-                points_per_game: 95.0,
-            },
-        ];
-
-        let season = 2022;
-        let (players_by_team, filtered_teams) = filter_data_by_season(&player_data, &team_data, season);
-
-        assert_eq!(players_by_team.len(), 1);
-        assert!(players_by_team.contains_key("TEA"));
-        assert_eq!(players_by_team["TEA"].len(), 1);
-        assert_eq!(players_by_team["TEA"][0].id, 1);
-
-        assert_eq!(filtered_teams.len(), 2);
-        assert_eq!(filtered_teams[0].abbreviation, "TEA");
-        assert_eq!(filtered_teams[1].abbreviation, "TEB");
+    fn test_parse_season_range() {
+        assert_eq!(parse_season_range("1997-2024").unwrap(), 1997..=2024);
+        assert_eq!(parse_season_range("2022").unwrap(), 2022..=2022);
+        assert!(parse_season_range("2024-1997").is_err());
+        assert!(parse_season_range("").is_err());
+    }
+
+    #[test]
+    fn test_flag_value() {
+        let args: Vec<String> = ["program", "--batch", "1997-2024", "--method"].iter().map(|a| a.to_string()).collect();
+
+        assert_eq!(flag_value(&args, "--batch"), Some("1997-2024"));
+        assert_eq!(flag_value(&args, "--method"), Some(""));
+        assert_eq!(flag_value(&args, "--out"), None);
+    }
+
+    #[test]
+    fn test_parse_minimums() {
+        let args: Vec<String> =
+            ["program", "--qualify", "--min-games", "40", "--min-3pa", "150"].iter().map(|a| a.to_string()).collect();
+        let minimums = parse_minimums(&args);
+
+        assert_eq!(minimums, Qualifier { min_games: Some(40), min_x3pa: Some(150.0), ..Default::default() });
+        // The 66 game lockout season keeps its prorated minutes and makes.
+        let lockout = Qualifier::basketball_reference(66).overridden_by(&minimums);
+        assert_eq!((lockout.min_games, lockout.min_minutes), (Some(40), Some(1208)));
+        assert_eq!((lockout.min_x3pa, lockout.min_fg_made), (Some(150.0), Some(242.0)));
+        assert_eq!(parse_minimums(&args[..2]), Qualifier::default());
+    }
+
+    #[test]
+    fn test_run_batch() {
+        let out_dir = Path::new("Batch Test Out");
+        let base = AnalysisScope {
+            leagues: LeagueFilter::only(&["NBA"]),
+            team_basis: StatBasis::Per100Poss,
+            ..Default::default()
+        };
+        let data = load_pipeline_data(
+            &AnalysisScope { seasons: 2021..=2022, ..base.clone() },
+            MultiTeamPolicy::MinutesWeighted,
+        );
+
+        let options = CorrelationOptions {
+            method: CorrelationMethod::Spearman,
+            bootstrap: Some(BootstrapOptions { resamples: 20, seed: 1 }),
+            adjustment: PValueAdjustment::BenjaminiHochberg,
+            ..Default::default()
+        };
+        let failures = run_batch(&data, 2021..=2022, &base, &options, out_dir).unwrap();
+        let season_file = fs::read_to_string(out_dir.join("2021").join(OUTPUT_FILES[2])).unwrap();
+        let combined = fs::read_to_string(out_dir.join(OUTPUT_FILES[2])).unwrap();
+        let all_files_exist = OUTPUT_FILES
+            .iter()
+            .all(|f| out_dir.join("2022").join(f).exists() && out_dir.join(f).exists());
+        fs::remove_dir_all(out_dir).unwrap();
+
+        assert!(failures.is_empty());
+        assert!(all_files_exist);
+        assert!(season_file.lines().skip(1).all(|l| l.starts_with("2021,")));
+        let seasons: Vec<&str> = combined.lines().skip(1).map(|l| &l[..4]).collect();
+        assert_eq!(combined.lines().next(), season_file.lines().next());
+        assert_eq!(seasons.iter().filter(|s| **s == "2021").count(), season_file.lines().count() - 1);
+        assert!(seasons.contains(&"2022"));
+        assert!(combined.lines().skip(1).all(|l| l.contains(",Spearman,") && l.contains(",Benjamini-Hochberg,")));
+        assert!(seasons.windows(2).all(|w| w[0] <= w[1]));
+        assert!(combined.contains("FG Percent vs Points Per 100 Poss"));
+        assert!(!combined.contains("Points Per Game"));
     }
 
     #[test]
     fn test_main() {
-        let result = std::panic::catch_unwind(|| {
-            main();
-        });
+        let out_dir = std::env::temp_dir().join(format!("nba-analysis-test-{}", std::process::id()));
+        let args: Vec<String> = ["program", "--out", out_dir.to_str().unwrap()].iter().map(|a| a.to_string()).collect();
+        let result = std::panic::catch_unwind(|| run(&args));
+        let all_files_exist = OUTPUT_FILES.iter().all(|f| out_dir.join(f).exists());
+        let _ = fs::remove_dir_all(&out_dir);
+
         assert!(result.is_ok());
+        assert!(all_files_exist);
     }
 }
\ No newline at end of file
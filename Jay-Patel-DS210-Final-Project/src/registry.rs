@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use crate::data_structures::{PlayByPlay, PlayerCareer, PlayerSeasonInfo};
+
+pub const PLAYER_METADATA_HEADERS: [&str; 6] = [
+    "Position",
+    "Age",
+    "Experience",
+    "Hall of Fame",
+    "On Court Plus Minus Per 100 Poss",
+    "Net Plus Minus Per 100 Poss",
+];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerMetadata {
+    pub position: Option<String>,
+    pub age: Option<u32>,
+    pub experience: Option<u32>,
+    pub hall_of_fame: Option<bool>,
+    pub on_court_plus_minus: Option<f64>,
+    pub net_plus_minus: Option<f64>,
+}
+
+impl PlayerMetadata {
+    // Unknown values are written as empty cells, matching PLAYER_METADATA_HEADERS.
+    pub fn to_record(&self) -> [String; 6] {
+        [
+            self.position.clone().unwrap_or_default(),
+            self.age.map(|a| a.to_string()).unwrap_or_default(),
+            self.experience.map(|e| e.to_string()).unwrap_or_default(),
+            self.hall_of_fame.map(|h| h.to_string()).unwrap_or_default(),
+            self.on_court_plus_minus.map(|p| p.to_string()).unwrap_or_default(),
+            self.net_plus_minus.map(|p| p.to_string()).unwrap_or_default(),
+        ]
+    }
+}
+
+// Career rows are keyed by player_id; season and play-by-play rows by seas_id, which is
+// unique per player, season and team stint and is shared with Player Shooting.csv.
+pub struct PlayerRegistry {
+    careers: HashMap<u32, PlayerCareer>,
+    seasons: HashMap<u32, PlayerSeasonInfo>,
+    play_by_play: HashMap<u32, PlayByPlay>,
+    ids_by_name: HashMap<(String, u32), Vec<u32>>,
+}
+
+impl PlayerRegistry {
+    pub fn new(careers: Vec<PlayerCareer>, seasons: Vec<PlayerSeasonInfo>, play_by_play: Vec<PlayByPlay>) -> Self {
+        let mut ids_by_name: HashMap<(String, u32), Vec<u32>> = HashMap::new();
+        for season in &seasons {
+            let ids = ids_by_name.entry((season.name.clone(), season.season)).or_default();
+            if !ids.contains(&season.player_id) {
+                ids.push(season.player_id);
+            }
+        }
+
+        PlayerRegistry {
+            careers: careers.into_iter().map(|c| (c.player_id, c)).collect(),
+            seasons: seasons.into_iter().map(|s| (s.seas_id, s)).collect(),
+            play_by_play: play_by_play.into_iter().map(|p| (p.seas_id, p)).collect(),
+            ids_by_name,
+        }
+    }
+
+    // Resolves a name to a player_id for files without ids; None when no player or more
+    // than one player with that name played in the season.
+    pub fn player_id_by_name(&self, name: &str, season: u32) -> Option<u32> {
+        match self.ids_by_name.get(&(name.to_string(), season))?.as_slice() {
+            [player_id] => Some(*player_id),
+            _ => None,
+        }
+    }
+
+    pub fn career(&self, player_id: u32) -> Option<&PlayerCareer> {
+        self.careers.get(&player_id)
+    }
+
+    pub fn season(&self, seas_id: u32) -> Option<&PlayerSeasonInfo> {
+        self.seasons.get(&seas_id)
+    }
+
+    pub fn metadata(&self, player_id: u32, seas_id: u32) -> PlayerMetadata {
+        let season = self.season(seas_id).filter(|s| s.player_id == player_id);
+        let play_by_play = self.play_by_play.get(&seas_id).filter(|p| p.player_id == player_id);
+
+        PlayerMetadata {
+            position: season.map(|s| s.position.clone()),
+            age: season.and_then(|s| s.age),
+            experience: season.map(|s| s.experience),
+            hall_of_fame: self.career(player_id).map(|c| c.hall_of_fame),
+            on_court_plus_minus: play_by_play.and_then(|p| p.on_court_plus_minus_per_100_poss),
+            net_plus_minus: play_by_play.and_then(|p| p.net_plus_minus_per_100_poss),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // James Harden's 2022 Brooklyn stint.
+    fn play_by_play() -> PlayByPlay {
+        PlayByPlay {
+            seas_id: 29983,
+            season: 2022,
+            player_id: 3880,
+            name: "James Harden".to_string(),
+            position: "PG".to_string(),
+            age: Some(32),
+            experience: Some(13),
+            lg: "NBA".to_string(),
+            team_abbreviation: "BRK".to_string(),
+            games: 44,
+            minutes_played: 1622,
+            pg_percent: Some(97.0),
+            sg_percent: Some(3.0),
+            sf_percent: None,
+            pf_percent: None,
+            c_percent: None,
+            on_court_plus_minus_per_100_poss: Some(2.5),
+            net_plus_minus_per_100_poss: Some(-1.5),
+            bad_pass_turnover: 0,
+            lost_ball_turnover: 0,
+            shooting_foul_committed: 0,
+            offensive_foul_committed: 0,
+            shooting_foul_drawn: 0,
+            offensive_foul_drawn: None,
+            points_generated_by_assists: 0,
+            and1: 0,
+            fga_blocked: 0,
+        }
+    }
+
+    fn registry() -> PlayerRegistry {
+        let careers = vec![PlayerCareer {
+            player_id: 3880,
+            name: "James Harden".to_string(),
+            birth_year: None,
+            hall_of_fame: false,
+            num_seasons: 15,
+            first_season: 2010,
+            last_season: 2024,
+        }];
+        let seasons = vec![PlayerSeasonInfo {
+            season: 2022,
+            seas_id: 29983,
+            player_id: 3880,
+            name: "James Harden".to_string(),
+            birth_year: None,
+            position: "PG".to_string(),
+            age: Some(32),
+            lg: "NBA".to_string(),
+            team_abbreviation: "BRK".to_string(),
+            experience: 13,
+        }];
+        PlayerRegistry::new(careers, seasons, vec![play_by_play()])
+    }
+
+    #[test]
+    fn test_metadata() {
+        let metadata = registry().metadata(3880, 29983);
+
+        assert_eq!(metadata.position.as_deref(), Some("PG"));
+        assert_eq!(metadata.age, Some(32));
+        assert_eq!(metadata.experience, Some(13));
+        assert_eq!(metadata.hall_of_fame, Some(false));
+        assert_eq!(metadata.on_court_plus_minus, Some(2.5));
+        assert_eq!(metadata.to_record(), ["PG", "32", "13", "false", "2.5", "-1.5"]);
+    }
+
+    #[test]
+    fn test_player_id_by_name() {
+        let registry = registry();
+
+        assert_eq!(registry.player_id_by_name("James Harden", 2022), Some(3880));
+        assert_eq!(registry.player_id_by_name("James Harden", 2021), None);
+        assert_eq!(registry.player_id_by_name("Jim Harden", 2022), None);
+    }
+
+    #[test]
+    fn test_metadata_unknown_player() {
+        let registry = registry();
+
+        assert_eq!(registry.metadata(1, 29983), PlayerMetadata::default());
+        assert_eq!(registry.metadata(1, 29983).to_record(), ["", "", "", "", "", ""]);
+        assert_eq!(registry.metadata(3880, 1).hall_of_fame, Some(false));
+    }
+}
@@ -0,0 +1,545 @@
+use std::cmp::Ordering;
+use std::f64::consts::{PI, SQRT_2};
+use std::fmt;
+use crate::correlation::CorrelationMethod;
+
+// How sure a correlation is: the number of pairs, a two-sided p-value for "no
+// correlation", and confidence intervals. Everything is NaN when there are too few pairs.
+// The adjusted p-value equals the p-value until `PValueAdjustment::apply` has looked at the
+// whole batch of tests, and `significant` compares it with 1 - the confidence level.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Significance {
+    pub n: usize,
+    pub p_value: f64,
+    pub adjustment: PValueAdjustment,
+    pub adjusted_p_value: f64,
+    pub significant: bool,
+    pub confidence_interval: (f64, f64),
+    pub bootstrap_interval: Option<(f64, f64)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BootstrapOptions {
+    pub resamples: usize,
+    pub seed: u64,
+}
+
+// Corrections for running many tests at once. Bonferroni and Holm bound the chance of any
+// false positive; Benjamini-Hochberg bounds the expected share of false positives among
+// the significant results, so it keeps more of them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PValueAdjustment {
+    #[default]
+    None,
+    Bonferroni,
+    Holm,
+    BenjaminiHochberg,
+}
+
+impl PValueAdjustment {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "none" => Some(PValueAdjustment::None),
+            "bonferroni" => Some(PValueAdjustment::Bonferroni),
+            "holm" => Some(PValueAdjustment::Holm),
+            "bh" | "benjamini-hochberg" => Some(PValueAdjustment::BenjaminiHochberg),
+            _ => None,
+        }
+    }
+
+    // Adjusted p-values in the order given. NaN p-values stay NaN and do not count as tests.
+    pub fn adjust(&self, p_values: &[f64]) -> Vec<f64> {
+        let mut order: Vec<usize> = (0..p_values.len()).filter(|&i| !p_values[i].is_nan()).collect();
+        order.sort_by(|&a, &b| p_values[a].total_cmp(&p_values[b]));
+        let m = order.len() as f64;
+
+        let mut adjusted = p_values.to_vec();
+        match self {
+            PValueAdjustment::None => {}
+            PValueAdjustment::Bonferroni => {
+                for &i in &order {
+                    adjusted[i] = (p_values[i] * m).min(1.0);
+                }
+            }
+            // Step-down: the k-th smallest is multiplied by m - k + 1 and never falls below the
+            // one before it.
+            PValueAdjustment::Holm => {
+                let mut running_max: f64 = 0.0;
+                for (k, &i) in order.iter().enumerate() {
+                    running_max = running_max.max((p_values[i] * (m - k as f64)).min(1.0));
+                    adjusted[i] = running_max;
+                }
+            }
+            // Step-up: the k-th smallest is multiplied by m / k and never rises above the one
+            // after it.
+            PValueAdjustment::BenjaminiHochberg => {
+                let mut running_min: f64 = 1.0;
+                for (k, &i) in order.iter().enumerate().rev() {
+                    running_min = running_min.min(p_values[i] * m / (k + 1) as f64);
+                    adjusted[i] = running_min;
+                }
+            }
+        }
+        adjusted
+    }
+
+    // Adjusts the p-values of `tests` as one batch and flags the ones below `alpha`.
+    pub fn apply<'a>(&self, tests: impl IntoIterator<Item = &'a mut Significance>, alpha: f64) {
+        let mut tests: Vec<&mut Significance> = tests.into_iter().collect();
+        let p_values: Vec<f64> = tests.iter().map(|t| t.p_value).collect();
+        for (test, adjusted_p_value) in tests.iter_mut().zip(self.adjust(&p_values)) {
+            test.adjustment = *self;
+            test.adjusted_p_value = adjusted_p_value;
+            test.significant = adjusted_p_value.partial_cmp(&alpha) == Some(Ordering::Less);
+        }
+    }
+}
+
+impl fmt::Display for PValueAdjustment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PValueAdjustment::None => "None",
+            PValueAdjustment::Bonferroni => "Bonferroni",
+            PValueAdjustment::Holm => "Holm",
+            PValueAdjustment::BenjaminiHochberg => "Benjamini-Hochberg",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// How correlations are computed and judged. The default is Pearson with a 95% interval,
+// no bootstrap, and unadjusted p-values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CorrelationOptions {
+    pub method: CorrelationMethod,
+    pub confidence_level: f64,
+    pub bootstrap: Option<BootstrapOptions>,
+    pub adjustment: PValueAdjustment,
+}
+
+impl Default for CorrelationOptions {
+    fn default() -> Self {
+        CorrelationOptions {
+            method: CorrelationMethod::default(),
+            confidence_level: 0.95,
+            bootstrap: None,
+            adjustment: PValueAdjustment::None,
+        }
+    }
+}
+
+impl CorrelationOptions {
+    pub fn significance_level(&self) -> f64 {
+        1.0 - self.confidence_level
+    }
+
+    // Correlates the weighted pairs and tests the result.
+    pub fn correlate(&self, x_values: &[f64], y_values: &[f64], weights: &[f64]) -> (f64, Significance) {
+        let r = self.method.correlate(x_values, y_values, weights);
+        (r, self.significance(x_values, y_values, weights, r))
+    }
+
+    // Pearson and Spearman use the t-test on r with n - 2 degrees of freedom; Kendall's tau
+    // uses its normal approximation. The interval comes from Fisher's z, with Fieller's
+    // standard errors for the rank correlations. The bootstrap interval, when asked for,
+    // takes percentiles of the correlation over resampled pairs. `n` is the number of pairs,
+    // but the tests use Kish's effective sample size (sum of weights)^2 / (sum of squared
+    // weights), which equals n when every weight is the same.
+    pub fn significance(&self, x_values: &[f64], y_values: &[f64], weights: &[f64], r: f64) -> Significance {
+        let n = x_values.len().min(y_values.len()).min(weights.len());
+        let alpha = self.significance_level();
+        let bootstrap_interval =
+            self.bootstrap.map(|b| bootstrap_interval(self.method, x_values, y_values, weights, alpha, b));
+        let weights = &weights[..n];
+        let nf = if n == 0 {
+            0.0
+        } else {
+            weights.iter().sum::<f64>().powi(2) / weights.iter().map(|w| w * w).sum::<f64>()
+        };
+
+        let p_value = match self.method {
+            _ if r.is_nan() || nf < 3.0 => f64::NAN,
+            CorrelationMethod::KendallTau => {
+                let z = 3.0 * r * (nf * (nf - 1.0)).sqrt() / (2.0 * (2.0 * nf + 5.0)).sqrt();
+                erfc(z.abs() / SQRT_2)
+            }
+            CorrelationMethod::Pearson | CorrelationMethod::Spearman => {
+                let df = nf - 2.0;
+                if r.abs() >= 1.0 {
+                    0.0
+                } else {
+                    let t = r * (df / (1.0 - r * r)).sqrt();
+                    incomplete_beta(df / 2.0, 0.5, df / (df + t * t))
+                }
+            }
+        };
+
+        let standard_error = match self.method {
+            CorrelationMethod::Pearson if nf > 3.0 => (1.0 / (nf - 3.0)).sqrt(),
+            CorrelationMethod::Spearman if nf > 3.0 => (1.06 / (nf - 3.0)).sqrt(),
+            CorrelationMethod::KendallTau if nf > 4.0 => (0.437 / (nf - 4.0)).sqrt(),
+            _ => f64::NAN,
+        };
+        let z = r.clamp(-1.0, 1.0).atanh();
+        let margin = normal_quantile(1.0 - alpha / 2.0) * standard_error;
+        let confidence_interval = ((z - margin).tanh(), (z + margin).tanh());
+
+        Significance {
+            n,
+            p_value,
+            adjustment: PValueAdjustment::None,
+            adjusted_p_value: p_value,
+            significant: p_value < alpha,
+            confidence_interval,
+            bootstrap_interval,
+        }
+    }
+}
+
+// SplitMix64. Small, seedable, and the same on every platform, so bootstrap intervals
+// can be reproduced.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+// Pairs are resampled with their weights. Resamples that come out NaN (every value the
+// same) are left out.
+fn bootstrap_interval(
+    method: CorrelationMethod,
+    x_values: &[f64],
+    y_values: &[f64],
+    weights: &[f64],
+    alpha: f64,
+    options: BootstrapOptions,
+) -> (f64, f64) {
+    let n = x_values.len().min(y_values.len()).min(weights.len());
+    if n < 3 {
+        return (f64::NAN, f64::NAN);
+    }
+
+    let mut rng = SplitMix64(options.seed);
+    let (mut x_sample, mut y_sample, mut weight_sample) = (vec![0.0; n], vec![0.0; n], vec![0.0; n]);
+    let mut estimates = Vec::with_capacity(options.resamples);
+    for _ in 0..options.resamples {
+        for i in 0..n {
+            let j = rng.below(n);
+            x_sample[i] = x_values[j];
+            y_sample[i] = y_values[j];
+            weight_sample[i] = weights[j];
+        }
+        let r = method.correlate(&x_sample, &y_sample, &weight_sample);
+        if !r.is_nan() {
+            estimates.push(r);
+        }
+    }
+
+    estimates.sort_by(f64::total_cmp);
+    (percentile(&estimates, alpha / 2.0), percentile(&estimates, 1.0 - alpha / 2.0))
+}
+
+// Linear interpolation between the closest ranks of sorted values.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let position = p.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let (low, high) = (position.floor() as usize, position.ceil() as usize);
+    sorted[low] + (sorted[high] - sorted[low]) * (position - low as f64)
+}
+
+// Lanczos approximation (g = 7, n = 9).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + i as f64 + 1.0));
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+// Continued fraction of the incomplete beta function, evaluated with Lentz's method.
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    d = 1.0 / if d.abs() < TINY { TINY } else { d };
+    let mut result = d;
+    for m in 1..300 {
+        let m = m as f64;
+        for numerator in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ] {
+            d = 1.0 + numerator * d;
+            d = 1.0 / if d.abs() < TINY { TINY } else { d };
+            c = 1.0 + numerator / c;
+            c = if c.abs() < TINY { TINY } else { c };
+            result *= d * c;
+        }
+        if (d * c - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    result
+}
+
+// Regularized incomplete beta function I_x(a, b).
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+// Complementary error function through the regularized upper incomplete gamma function,
+// erfc(x) = Q(1/2, x^2) for x >= 0.
+fn erfc(x: f64) -> f64 {
+    if x < 0.0 {
+        return 2.0 - erfc(-x);
+    }
+    let (a, y) = (0.5, x * x);
+    if y == 0.0 {
+        return 1.0;
+    }
+    let front = (-y + a * y.ln() - ln_gamma(a)).exp();
+    if y < a + 1.0 {
+        // Series for the lower function P.
+        let (mut term, mut sum, mut k) = (1.0 / a, 1.0 / a, a);
+        while term.abs() > sum.abs() * 1e-16 {
+            k += 1.0;
+            term *= y / k;
+            sum += term;
+        }
+        1.0 - front * sum
+    } else {
+        // Continued fraction for Q, Lentz's method.
+        const TINY: f64 = 1e-300;
+        let mut b = y + 1.0 - a;
+        let mut c = 1.0 / TINY;
+        let mut d = 1.0 / b;
+        let mut result = d;
+        for i in 1..300 {
+            let numerator = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = numerator * d + b;
+            d = 1.0 / if d.abs() < TINY { TINY } else { d };
+            c = b + numerator / c;
+            c = if c.abs() < TINY { TINY } else { c };
+            result *= d * c;
+            if (d * c - 1.0).abs() < 1e-15 {
+                break;
+            }
+        }
+        front * result
+    }
+}
+
+// Inverse of the standard normal distribution function (Acklam's algorithm, relative
+// error below 1.2e-9).
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    if p == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p == 1.0 {
+        return f64::INFINITY;
+    }
+    if !(0.0..1.0).contains(&p) {
+        return f64::NAN;
+    }
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - P_LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distribution_functions() {
+        assert!((ln_gamma(5.0) - 24f64.ln()).abs() < 1e-12);
+        assert!((ln_gamma(0.5) - PI.sqrt().ln()).abs() < 1e-12);
+        assert!((incomplete_beta(2.0, 3.0, 0.4) - 0.5248).abs() < 1e-12);
+        assert_eq!(erfc(0.0), 1.0);
+        assert!((erfc(1.0) - 0.157_299_207_050_285_1).abs() < 1e-12);
+        assert!((erfc(-0.5) - 1.520_499_877_813_047).abs() < 1e-12);
+        assert!((normal_quantile(0.975) - 1.959_963_984_540_054).abs() < 1e-8);
+        assert!((normal_quantile(0.01) + 2.326_347_874_040_841).abs() < 1e-8);
+        assert_eq!(normal_quantile(0.5), 0.0);
+    }
+
+    #[test]
+    fn test_pearson_significance() {
+        let options = CorrelationOptions::default();
+        let x_values: Vec<f64> = (0..10).map(f64::from).collect();
+
+        // r = 0.5 over 10 pairs: t = 1.633 on 8 degrees of freedom.
+        let significance = options.significance(&x_values, &x_values, &[1.0; 10], 0.5);
+        assert_eq!(significance.n, 10);
+        assert!((significance.p_value - 0.141_1).abs() < 1e-3);
+        let (low, high) = significance.confidence_interval;
+        assert!((low + 0.189_2).abs() < 1e-3);
+        assert!((high - 0.859_3).abs() < 1e-3);
+        assert_eq!(significance.bootstrap_interval, None);
+
+        assert_eq!(options.significance(&x_values, &x_values, &[1.0; 10], 1.0).p_value, 0.0);
+        assert!(options.significance(&x_values[..2], &x_values[..2], &[1.0; 2], 1.0).p_value.is_nan());
+        assert!(options.significance(&x_values, &x_values, &[1.0; 10], f64::NAN).confidence_interval.0.is_nan());
+
+        // Ten pairs, two of them at a quarter weight, count as 8.5^2 / 8.125 = 8.89 pairs.
+        let mut weights = [1.0; 10];
+        weights[..2].copy_from_slice(&[0.25, 0.25]);
+        let weighted = options.significance(&x_values, &x_values, &weights, 0.5);
+        assert_eq!(weighted.n, 10);
+        assert!(weighted.p_value > significance.p_value);
+    }
+
+    #[test]
+    fn test_kendall_significance() {
+        let options = CorrelationOptions { method: CorrelationMethod::KendallTau, ..Default::default() };
+        let x_values: Vec<f64> = (0..20).map(f64::from).collect();
+
+        let significance = options.significance(&x_values, &x_values, &[1.0; 20], 0.3);
+        // z = 3 * 0.3 * sqrt(380) / sqrt(90) = 1.849
+        assert!((significance.p_value - 0.064_4).abs() < 1e-3);
+        assert!(significance.confidence_interval.0 < 0.3 && 0.3 < significance.confidence_interval.1);
+    }
+
+    #[test]
+    fn test_bootstrap_is_reproducible() {
+        let x_values: Vec<f64> = (0..40).map(|i| i as f64).collect();
+        let y_values: Vec<f64> = x_values.iter().map(|x| x + ((x * 7.0) % 11.0)).collect();
+        let options = CorrelationOptions {
+            bootstrap: Some(BootstrapOptions { resamples: 500, seed: 7 }),
+            ..Default::default()
+        };
+
+        let (r, first) = options.correlate(&x_values, &y_values, &[1.0; 40]);
+        let (_, second) = options.correlate(&x_values, &y_values, &[1.0; 40]);
+        let (low, high) = first.bootstrap_interval.unwrap();
+
+        assert_eq!(first, second);
+        assert!(low < r && r < high);
+        assert!(high <= 1.0);
+        let other_seed = CorrelationOptions { bootstrap: Some(BootstrapOptions { resamples: 500, seed: 8 }), ..options };
+        assert_ne!(other_seed.correlate(&x_values, &y_values, &[1.0; 40]).1.bootstrap_interval, first.bootstrap_interval);
+    }
+
+    #[test]
+    fn test_p_value_adjustments() {
+        let p_values = [0.01, 0.04, 0.03, 0.005, f64::NAN];
+        let close = |adjusted: Vec<f64>, expected: [f64; 4]| {
+            adjusted[4].is_nan() && adjusted.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-12)
+        };
+
+        assert!(close(PValueAdjustment::None.adjust(&p_values), [0.01, 0.04, 0.03, 0.005]));
+        assert!(close(PValueAdjustment::Bonferroni.adjust(&p_values), [0.04, 0.16, 0.12, 0.02]));
+        assert!(close(PValueAdjustment::Holm.adjust(&p_values), [0.03, 0.06, 0.06, 0.02]));
+        assert!(close(PValueAdjustment::BenjaminiHochberg.adjust(&p_values), [0.02, 0.04, 0.04, 0.02]));
+        assert_eq!(PValueAdjustment::Bonferroni.adjust(&[0.4, 0.5]), vec![0.8, 1.0]);
+        assert!(PValueAdjustment::Holm.adjust(&[]).is_empty());
+        assert_eq!(PValueAdjustment::parse("BH"), Some(PValueAdjustment::BenjaminiHochberg));
+        assert_eq!(PValueAdjustment::parse("fdr"), None);
+    }
+
+    #[test]
+    fn test_apply_adjustment() {
+        let options = CorrelationOptions::default();
+        let x_values: Vec<f64> = (0..30).map(f64::from).collect();
+        let mut tests: Vec<Significance> =
+            [0.2, 0.4, 0.5].iter().map(|&r| options.significance(&x_values, &x_values, &[1.0; 30], r)).collect();
+        let unadjusted: Vec<bool> = tests.iter().map(|t| t.significant).collect();
+
+        PValueAdjustment::Bonferroni.apply(&mut tests, options.significance_level());
+
+        assert_eq!(unadjusted, vec![false, true, true]);
+        // r = 0.4 over 30 pairs has p = 0.029, which is not below 0.05 / 3.
+        assert_eq!(tests.iter().map(|t| t.significant).collect::<Vec<_>>(), vec![false, false, true]);
+        assert_eq!(tests[1].adjustment, PValueAdjustment::Bonferroni);
+        assert!((tests[1].adjusted_p_value - 3.0 * tests[1].p_value).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_percentile() {
+        assert_eq!(percentile(&[1.0, 2.0, 3.0, 4.0, 5.0], 0.5), 3.0);
+        assert_eq!(percentile(&[1.0, 2.0], 0.25), 1.25);
+        assert!(percentile(&[], 0.5).is_nan());
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::data_structures::{StatBasis, Team, TeamBoxScore, TeamSeason};
+
+// Per game values in the files are rounded to one decimal, so totals / games can be off
+// by up to half of that.
+const PER_GAME_TOLERANCE: f64 = 0.05 + 1e-9;
+const PERCENT_TOLERANCE: f64 = 1e-9;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConsistencyIssue {
+    pub season: u32,
+    pub lg: String,
+    pub abbreviation: String,
+    pub stat: String,
+    pub expected: f64,
+    pub found: f64,
+}
+
+impl fmt::Display for ConsistencyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}: {} expected {} but found {}",
+            self.season, self.lg, self.abbreviation, self.stat, self.expected, self.found
+        )
+    }
+}
+
+// Team seasons keyed by (season, lg, abbreviation). The league is part of the key because
+// the league average rows ("NA") exist once per league in BAA/ABA/NBA overlap years.
+pub struct TeamSeasonTable {
+    seasons: HashMap<(u32, String, String), TeamSeason>,
+}
+
+impl TeamSeasonTable {
+    pub fn build(per_game: Vec<TeamBoxScore>, per_100_poss: Vec<TeamBoxScore>, totals: Vec<TeamBoxScore>) -> Self {
+        let mut seasons: HashMap<(u32, String, String), TeamSeason> = HashMap::new();
+
+        for box_score in per_game.into_iter().chain(per_100_poss).chain(totals) {
+            let key = (box_score.season, box_score.lg.clone(), box_score.abbreviation.clone());
+            let team_season = seasons.entry(key).or_insert_with(|| TeamSeason {
+                season: box_score.season,
+                lg: box_score.lg.clone(),
+                name: box_score.name.clone(),
+                abbreviation: box_score.abbreviation.clone(),
+                playoffs: box_score.playoffs,
+                games: box_score.games,
+                per_game: None,
+                per_100_poss: None,
+                totals: None,
+            });
+            team_season.games = team_season.games.or(box_score.games);
+            match box_score.basis {
+                StatBasis::PerGame => team_season.per_game = Some(box_score),
+                StatBasis::Per100Poss => team_season.per_100_poss = Some(box_score),
+                StatBasis::Totals => team_season.totals = Some(box_score),
+            }
+        }
+
+        TeamSeasonTable { seasons }
+    }
+
+    pub fn get(&self, season: u32, lg: &str, abbreviation: &str) -> Option<&TeamSeason> {
+        self.seasons.get(&(season, lg.to_string(), abbreviation.to_string()))
+    }
+
+    // Attaches each team's season from the table so its stats can be read in any basis.
+    pub fn merge_into(&self, teams: &mut [Team]) {
+        for team in teams {
+            team.season_stats = self.get(team.season, &team.lg, &team.abbreviation).cloned();
+        }
+    }
+
+    // Attaches each team's opponent season, for a table built from the opponent files.
+    pub fn merge_opponents_into(&self, teams: &mut [Team]) {
+        for team in teams {
+            team.opponent_stats = self.get(team.season, &team.lg, &team.abbreviation).cloned();
+        }
+    }
+
+    // Checks that every shape agrees on games played and shooting percentages, and that
+    // totals / games matches the per game row. League average rows are averages over
+    // teams rather than totals over games, so they are expected to show up here.
+    pub fn check_consistency(&self) -> Vec<ConsistencyIssue> {
+        let mut issues = Vec::new();
+        for team_season in self.seasons.values() {
+            let mut issue = |stat: &str, expected: f64, found: f64| {
+                issues.push(ConsistencyIssue {
+                    season: team_season.season,
+                    lg: team_season.lg.clone(),
+                    abbreviation: team_season.abbreviation.clone(),
+                    stat: stat.to_string(),
+                    expected,
+                    found,
+                })
+            };
+
+            let shapes = [&team_season.per_game, &team_season.per_100_poss, &team_season.totals];
+            for box_score in shapes.into_iter().flatten() {
+                if let (Some(expected), Some(found)) = (team_season.games, box_score.games) {
+                    if expected != found {
+                        issue("g", expected as f64, found as f64);
+                    }
+                }
+            }
+
+            if let Some(per_game) = &team_season.per_game {
+                for box_score in [&team_season.per_100_poss, &team_season.totals].into_iter().flatten() {
+                    for ((stat, expected), (_, found)) in per_game.percentages().into_iter().zip(box_score.percentages()) {
+                        if let (Some(expected), Some(found)) = (expected, found) {
+                            if (expected - found).abs() > PERCENT_TOLERANCE {
+                                issue(stat, expected, found);
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let (Some(per_game), Some(totals), Some(games)) =
+                (&team_season.per_game, &team_season.totals, team_season.games.filter(|&g| g > 0))
+            {
+                for ((stat, expected), (_, total)) in per_game.counting_stats().into_iter().zip(totals.counting_stats()) {
+                    if let (Some(expected), Some(total)) = (expected, total) {
+                        let found = total / games as f64;
+                        if (expected - found).abs() > PER_GAME_TOLERANCE {
+                            issue(stat, expected, found);
+                        }
+                    }
+                }
+            }
+        }
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_loader::{load_team_box_scores, LoadOptions};
+    use crate::data_structures::OpponentShooting;
+
+    fn box_score(basis: StatBasis, games: u32, pts: f64, fg_percent: f64) -> TeamBoxScore {
+        TeamBoxScore {
+            season: 2022,
+            lg: "NBA".to_string(),
+            name: "Team A".to_string(),
+            abbreviation: "TEA".to_string(),
+            playoffs: true,
+            games: Some(games),
+            basis,
+            minutes: None,
+            fg: None,
+            fga: None,
+            fg_percent: Some(fg_percent),
+            x3p: None,
+            x3pa: None,
+            x3p_percent: None,
+            x2p: None,
+            x2pa: None,
+            x2p_percent: None,
+            ft: None,
+            fta: None,
+            ft_percent: None,
+            orb: None,
+            drb: None,
+            trb: None,
+            ast: None,
+            stl: None,
+            blk: None,
+            tov: None,
+            pf: None,
+            pts: Some(pts),
+        }
+    }
+
+    #[test]
+    fn test_build_and_check_consistency() {
+        let table = TeamSeasonTable::build(
+            vec![box_score(StatBasis::PerGame, 82, 110.0, 0.47)],
+            vec![box_score(StatBasis::Per100Poss, 82, 112.3, 0.47)],
+            vec![box_score(StatBasis::Totals, 82, 9020.0, 0.47)],
+        );
+
+        assert_eq!(table.seasons.len(), 1);
+        let team_season = table.get(2022, "NBA", "TEA").unwrap();
+        assert_eq!(team_season.stats(StatBasis::Per100Poss).unwrap().pts, Some(112.3));
+        assert_eq!(team_season.stats(StatBasis::Totals).unwrap().pts, Some(9020.0));
+        assert!(table.get(2022, "ABA", "TEA").is_none());
+        assert!(table.check_consistency().is_empty());
+    }
+
+    #[test]
+    fn test_check_consistency_reports_mismatches() {
+        let table = TeamSeasonTable::build(
+            vec![box_score(StatBasis::PerGame, 82, 110.0, 0.47)],
+            vec![box_score(StatBasis::Per100Poss, 82, 112.3, 0.46)],
+            vec![box_score(StatBasis::Totals, 81, 8900.0, 0.47)],
+        );
+
+        let mut stats: Vec<String> = table.check_consistency().into_iter().map(|i| i.stat).collect();
+        stats.sort();
+        assert_eq!(stats, vec!["fg_percent", "g", "pts"]);
+    }
+
+    #[test]
+    fn test_check_consistency_real_data() {
+        let options = LoadOptions::default();
+        let (per_game, _) = load_team_box_scores(
+            "NBA Stats (1947-Present)/Team Stats Per Game.csv",
+            StatBasis::PerGame,
+            &options,
+        )
+        .unwrap();
+        let (per_100_poss, _) = load_team_box_scores(
+            "NBA Stats (1947-Present)/Team Stats Per 100 Poss.csv",
+            StatBasis::Per100Poss,
+            &options,
+        )
+        .unwrap();
+        let (totals, _) =
+            load_team_box_scores("NBA Stats (1947-Present)/Team Totals.csv", StatBasis::Totals, &options).unwrap();
+
+        let table = TeamSeasonTable::build(per_game, per_100_poss, totals);
+        assert_eq!(table.seasons.len(), 1845);
+        assert_eq!(table.seasons.values().filter(|t| t.per_100_poss.is_some()).count(), 1402);
+
+        let issues = table.check_consistency();
+        assert!(!issues.is_empty());
+        assert!(issues.iter().all(|i| i.abbreviation == "NA"));
+    }
+
+    #[test]
+    fn test_merge_into_reads_points_by_basis() {
+        let table = TeamSeasonTable::build(
+            vec![box_score(StatBasis::PerGame, 82, 110.0, 0.47)],
+            vec![box_score(StatBasis::Per100Poss, 82, 112.3, 0.47)],
+            vec![box_score(StatBasis::Totals, 82, 9020.0, 0.47)],
+        );
+        let mut teams = vec![
+            Team { points_per_game: Some(109.9), ..Team::test_fixture() },
+            Team { abbreviation: "TEB".to_string(), points_per_game: Some(101.0), ..Team::test_fixture() },
+        ];
+        table.merge_into(&mut teams);
+
+        assert_eq!(teams[0].points(StatBasis::PerGame), Some(110.0));
+        assert_eq!(teams[0].points(StatBasis::Per100Poss), Some(112.3));
+        assert_eq!(teams[0].points(StatBasis::Totals), Some(9020.0));
+        // Without a table row only the team file's per game column is available.
+        assert_eq!(teams[1].points(StatBasis::PerGame), Some(101.0));
+        assert_eq!(teams[1].points(StatBasis::Totals), None);
+    }
+
+    #[test]
+    fn test_merge_opponents_into_reads_fg_percent_by_basis() {
+        // Per 100 possession opponent rows only start in 1974, so a season can lack one.
+        let opponents = TeamSeasonTable::build(
+            vec![box_score(StatBasis::PerGame, 82, 108.0, 0.461)],
+            Vec::new(),
+            vec![box_score(StatBasis::Totals, 82, 8856.0, 0.4612)],
+        );
+        let mut teams = vec![
+            Team { opponent: Some(OpponentShooting { fg_percent: Some(0.46), ..Default::default() }), ..Team::test_fixture() },
+            Team {
+                abbreviation: "TEB".to_string(),
+                opponent: Some(OpponentShooting { fg_percent: Some(0.45), ..Default::default() }),
+                ..Team::test_fixture()
+            },
+        ];
+        opponents.merge_opponents_into(&mut teams);
+
+        assert_eq!(teams[0].opponent_fg_percent(StatBasis::PerGame), Some(0.461));
+        assert_eq!(teams[0].opponent_fg_percent(StatBasis::Totals), Some(0.4612));
+        assert_eq!(teams[0].opponent_fg_percent(StatBasis::Per100Poss), None);
+        // Without an opponent season only the per game block is available.
+        assert_eq!(teams[1].opponent_fg_percent(StatBasis::PerGame), Some(0.45));
+        assert_eq!(teams[1].opponent_fg_percent(StatBasis::Totals), None);
+    }
+}